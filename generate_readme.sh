@@ -80,6 +80,90 @@ madu --hotspots --top 5 src/
 madu --watch 5 src/
 ```
 
+## Configuration
+
+madu looks for a `.madu.toml` in the analyzed path and its parent directories (or uses `--config <file>`). Top-level keys set defaults for any long flag, and `[profiles.<name>]` tables bundle settings that `--profile <name>` applies:
+
+```toml
+exclude = ["*.lock", "*.json"]
+no-noise = true
+days = 30
+
+[profiles.review]
+hotspots = true
+top = 20
+blame = true
+```
+
+An `[authors]` table merges identities that git sees as different people, on top of `.mailmap`. Keys are author names or emails:
+
+```toml
+[authors]
+"jane@laptop.local" = "Jane Doe"
+"Jane D" = "Jane Doe"
+```
+
+Use `--author-email` to identify authors by email instead of name, and `--exclude-author 'dependabot*'` to leave bots out of every git metric.
+
+Settings resolve as command line > `MADU_*` environment variables > profile > config file > built-in defaults. `madu --print-config` shows the effective value of each setting and where it came from.
+
+## Quality Gates
+
+`--fail-if` makes madu exit with status 3 when a rule is violated, so CI can fail the build. Rules compare a metric against a limit per file, or against the run's total with a `total.` prefix:
+
+```bash
+madu src --fail-if 'complexity>40' --fail-if 'duplicates>30%' --fail-if 'total.lines>200000'
+```
+
+In `.madu.toml`, `[[gates]]` entries can restrict rules to globs. When several rules for a metric match a file, the last one wins, and `--fail-if` rules come after the config file's:
+
+```toml
+[[gates]]
+fail-if = ["complexity>40", "duplicates>30%"]
+
+[[gates]]
+fail-if = "complexity>120"
+paths = ["src/legacy/**"]
+```
+
+The report is printed as usual, followed by a summary of violations on stderr.
+
+The report is printed as usual, showing only the metrics you selected even when a rule checks another one, followed by a summary of violations on stderr.
+
+To stop a legacy codebase from getting worse without fixing it first, record a baseline and compare later runs against it:
+
+```bash
+madu src --complexity --save-baseline .madu-baseline.json
+madu src --complexity --compare-baseline .madu-baseline.json --ratchet
+```
+
+The comparison lists only files whose values got worse (up for most metrics, down for bus factor and isolation), new files (at or above `--min-value`, if given), files that improved and files that were removed. With `--ratchet`, any regression makes madu exit with status 3. Both flags can name the same file to refresh the baseline after a passing comparison.
+
+## Caching
+
+Results that depend only on a file's contents (lines, complexity, density, the chunks behind `--duplicates`, ...) are cached between runs, keyed by path, size and modification time, with a content hash as fallback. Unchanged files aren't read again, which also speeds up every `--watch` tick. Git metrics are always recomputed.
+
+The cache lives in `$XDG_CACHE_HOME/madu` (or `~/.cache/madu`), or in a `.madu-cache/` directory in the analyzed path if one exists. Use `--no-cache` to bypass it and `--clear-cache` to start fresh.
+
+## Library Usage
+
+The analyzers are also available as the `mad_useful` library crate:
+
+```rust
+use mad_useful::{Analyzer, metrics::find_metric};
+
+let report = Analyzer::new("src")
+    .metric(find_metric("complexity").unwrap())
+    .metric(find_metric("size").unwrap())
+    .no_noise(true)
+    .top(10)
+    .run();
+
+for row in &report.rows {
+    println!("{:?} {}", report.value(row, "complexity"), row.path.display());
+}
+```
+
 EOF
 
 # Add help output
@@ -108,6 +192,10 @@ run_command "Summary by file extension" "cargo run -- --summary src"
 echo "Groups results by file extension. Useful for understanding codebase composition." >> "$README_FILE"
 echo "" >> "$README_FILE"
 
+run_command "Lines of code by language" "cargo run -- --loc --summary ."
+echo "Splits every file into code, comment and blank lines like cloc, using each language's comment and string syntax; Python docstrings count as comments, as in cloc. Languages are detected by extension, well-known file names (\`Makefile\`, \`Dockerfile\`) and \`#!\` lines. The summary is cloc's table, each language's files, blank, comment and code lines, with \`mixed\` counting the code lines that also carry a comment. Without \`--summary\` it lists files, and \`--dirs\` totals directories." >> "$README_FILE"
+echo "" >> "$README_FILE"
+
 run_command "Top 5 largest files" "cargo run -- --top 5 src"
 echo "Limits output to top N results. Essential for focusing on the most significant files." >> "$README_FILE"
 echo "" >> "$README_FILE"
@@ -124,7 +212,12 @@ echo "" >> "$README_FILE"
 
 run_command "Cyclomatic complexity analysis" "cargo run -- --complexity --top 5 src"
 echo "> [!IMPORTANT]" >> "$README_FILE"
-echo "> Measures code complexity using control flow analysis. Higher values indicate more complex, harder-to-test code that may need refactoring." >> "$README_FILE"
+echo "> Cyclomatic complexity: one plus every decision point in the file. Each \`if\`, loop, \`case\` or \`catch\` arm, \`&&\`, \`||\` and ternary counts once, with each language's own branches (Rust \`match\` arms, Python \`elif\`/\`except\`, Go \`select\` cases, Kotlin \`when\` and \`?:\`, Swift \`guard\`, \`??\` in C#, Swift, JavaScript and PHP). \`return\`, \`break\`, Rust's \`?\` and optional chaining don't branch, and neither do comments or strings. Higher values indicate more complex, harder-to-test code that may need refactoring." >> "$README_FILE"
+echo "" >> "$README_FILE"
+
+run_command "Most complex functions" "cargo run -- --functions --top 5 src"
+echo "> [!IMPORTANT]" >> "$README_FILE"
+echo "> Finds the functions and methods of Rust, C/C++, C#, Java, Kotlin, Scala, Swift, JavaScript/TypeScript, Python, Go and PHP files and ranks them by cyclomatic complexity (one plus the decision points in the body), with \`path:line\`. Nested functions and closures with a body of their own are counted separately. Per file, \`--metric function-max\`, \`function-mean\` and \`function-p90\` report the highest, average and 90th percentile function complexity, so \`--fail-if 'function-max>25'\` can keep any single function from growing too complex." >> "$README_FILE"
 echo "" >> "$README_FILE"
 
 run_command "Code density calculation" "cargo run -- --density --threshold 75 src"
@@ -147,6 +240,11 @@ echo "> [!IMPORTANT]" >> "$README_FILE"
 echo "> Measures deepest nesting level. High values may indicate overly complex functions needing refactoring." >> "$README_FILE"
 echo "" >> "$README_FILE"
 
+run_command "Several metrics in one pass" "cargo run -- --complexity --size --indent --top 5 src"
+echo "> [!IMPORTANT]" >> "$README_FILE"
+echo "> Analysis flags can be combined. Each file is read once and every requested metric gets its own column. Sorting and \`--top\`/\`--min-value\`/\`--threshold\` use the first column." >> "$README_FILE"
+echo "" >> "$README_FILE"
+
 echo "#### Advanced Code Analysis" >> "$README_FILE"
 echo "" >> "$README_FILE"
 
@@ -173,6 +271,11 @@ if git rev-parse --git-dir > /dev/null 2>&1; then
     echo "> Shows commit frequency in recent timeframe. High churn files change often and may need architectural review." >> "$README_FILE"
     echo "" >> "$README_FILE"
     
+    run_command "Churn in changed lines" "cargo run -- --churn --churn-by changed --days 30 --top 10 src"
+    echo "> [!IMPORTANT]" >> "$README_FILE"
+    echo "> Counts lines instead of commits, so a typo fix weighs less than a rewrite. \`--churn-by\` takes \`added\`, \`deleted\`, \`changed\` or \`relative\` (changed lines per current line, in percent), and also sets the churn factor of \`--hotspots\`." >> "$README_FILE"
+    echo "" >> "$README_FILE"
+    
     run_command "File staleness analysis" "cargo run -- --age --threshold 90 src"
     echo "> [!IMPORTANT]" >> "$README_FILE"
     echo "> Days since last modification. Helps identify stale code and recent activity patterns. Shows only oldest 10% of files." >> "$README_FILE"
@@ -183,6 +286,16 @@ if git rev-parse --git-dir > /dev/null 2>&1; then
     echo "> Commit frequency variation score. High values indicate irregular development patterns that may need attention." >> "$README_FILE"
     echo "" >> "$README_FILE"
     
+    run_command "Churn over a revision range" "cargo run -- --churn --ownership --rev-range HEAD~10..HEAD src"
+    echo "> [!IMPORTANT]" >> "$README_FILE"
+    echo "> \`--since\`/\`--until\` (dates), \`--rev-range\` and \`--ref <branch>\` limit the history every git metric looks at, e.g. what changed in a release or on a PR branch. With any of them, churn counts every commit in the range instead of the last \`--days\`." >> "$README_FILE"
+    echo "" >> "$README_FILE"
+    
+    run_command "Files with their former names" "cargo run -- --churn --show-renames --top 5 src"
+    echo "> [!IMPORTANT]" >> "$README_FILE"
+    echo "> Git metrics follow renames and moves, so churn, ownership, age and rhythm cover a file's whole lineage. \`--show-renames\` lists the names a file had before." >> "$README_FILE"
+    echo "" >> "$README_FILE"
+    
     echo "#### Code Ownership & Attribution" >> "$README_FILE"
     echo "" >> "$README_FILE"
     
@@ -191,6 +304,16 @@ if git rev-parse --git-dir > /dev/null 2>&1; then
     echo "> Percentage of commits by primary author. Shows files with >70% single-author ownership (potential knowledge silos)." >> "$README_FILE"
     echo "" >> "$README_FILE"
     
+    run_command "Ownership of surviving lines" "cargo run -- --blame-lines --dirs src"
+    echo "> [!IMPORTANT]" >> "$README_FILE"
+    echo "> Share of current lines last written by the top author, from \`git blame\`. Unlike \`--ownership\`, code that was rewritten no longer counts for its original author." >> "$README_FILE"
+    echo "" >> "$README_FILE"
+    
+    run_command "Bus factor per directory" "cargo run -- --bus-factor --depth 1 ."
+    echo "> [!IMPORTANT]" >> "$README_FILE"
+    echo "> Fewest authors who together made more than half of a directory's commits (or wrote more than half of its surviving lines with \`--blame-lines\`). A bus factor of 1 is flagged with the one person the directory depends on. Directories are ranked lowest bus factor first, so \`--top\` shows the most at-risk ones." >> "$README_FILE"
+    echo "" >> "$README_FILE"
+    
     run_command "Git attribution by author" "cargo run -- --blame --author 'john' src"
     echo "> [!IMPORTANT]" >> "$README_FILE"
     echo "> Shows primary author name per file, filtered by specific author. Useful for code review assignments." >> "$README_FILE"
@@ -201,6 +324,11 @@ if git rev-parse --git-dir > /dev/null 2>&1; then
     echo "> Percentage of single-file commits. High isolation suggests focused, atomic changes. Shows files with >50% isolated commits." >> "$README_FILE"
     echo "" >> "$README_FILE"
     
+    run_command "Files that change together" "cargo run -- --coupling --days 365 --top 5 ."
+    echo "> [!IMPORTANT]" >> "$README_FILE"
+    echo "> Pairs of files changed in the same commits: shared commits (support), then the share of the less active file's commits that also touched the other (confidence). Pairs need at least 2 shared commits unless \`--min-value\` says otherwise, and commits touching more than 50 files are ignored. Add \`--dirs\` to find directories that depend on each other." >> "$README_FILE"
+    echo "" >> "$README_FILE"
+    
     echo "#### Risk Assessment" >> "$README_FILE"
     echo "" >> "$README_FILE"
     
@@ -208,6 +336,32 @@ if git rev-parse --git-dir > /dev/null 2>&1; then
     echo "> [!IMPORTANT]" >> "$README_FILE"
     echo "> Combines complexity and change frequency. High scores identify files that are both complex and frequently modified - prime refactoring candidates." >> "$README_FILE"
     echo "" >> "$README_FILE"
+    
+    run_command "Hotspots weighted by lines changed" "cargo run -- --hotspots --churn-by changed --top 5 src"
+    
+    run_command "Custom hotspot score" "cargo run -- --hotspots --score '0.5*norm(complexity) + 0.3*norm(churn-lines) + 0.2*norm(author-count)' --top 5 src"
+    echo "> [!IMPORTANT]" >> "$README_FILE"
+    echo "> \`--score\` (or \`score = \"...\"\` in \`.madu.toml\`) ranks hotspots by any metrics: products with \`*\`, weighted sums with \`+\`, and \`norm(metric)\` to scale a metric to 0-100 of the highest file so different units can be mixed. Each file lists the factor values behind its score." >> "$README_FILE"
+    echo "" >> "$README_FILE"
+    
+    echo "#### Trends Over Time" >> "$README_FILE"
+    echo "" >> "$README_FILE"
+    
+    run_command "Complexity and size over the last year" "cargo run -- trend --every 2w --since 1y --complexity --metric lines src"
+    echo "> [!IMPORTANT]" >> "$README_FILE"
+    echo "> Evaluates content metrics at the commit current every \`--every\` interval (\`h\`, \`d\`, \`w\`, \`m\`onths, \`y\`) back to \`--since\`, reading files straight from git without checking anything out. \`--by file\` or \`--by dir\` gives a series per file or directory, \`--ref\` samples another branch, and \`--format json\`/\`csv\` exports the series." >> "$README_FILE"
+    echo "" >> "$README_FILE"
+
+    echo "#### Comparing Revisions" >> "$README_FILE"
+    echo "" >> "$README_FILE"
+
+    run_command "What the last 10 commits did to complexity" "cargo run -- diff HEAD~10 HEAD --complexity src"
+    echo "Lists every file whose value changed between the two revisions, largest regression first, then added and removed files, with the before → after values and the change in each metric's total. Either side can also be a directory, e.g. \`madu diff old-release/ new-release/\`, which compares two trees on disk with any metric; revisions are read straight from git and support every metric that doesn't need git history." >> "$README_FILE"
+    echo "" >> "$README_FILE"
+
+    run_command "Analyze an older commit without checking it out" "cargo run -- --git-ref HEAD~10 --complexity --top 5 src"
+    echo "\`--git-ref\` reads the files of a branch or commit straight from the object store and uses its history, so every metric works without a checkout; \`--ref\` only swaps the history and still reads the working directory. Bare repositories, such as build-server mirrors, are analyzed at \`HEAD\` by default: \`madu --hotspots /srv/mirrors/project.git\`." >> "$README_FILE"
+    echo "" >> "$README_FILE"
 fi
 
 echo "### Directory Analysis" >> "$README_FILE"
@@ -268,6 +422,19 @@ echo "> [!IMPORTANT]" >> "$README_FILE"
 echo "> Filter results by git author name. Shows recent changes by specific team member." >> "$README_FILE"
 echo "" >> "$README_FILE"
 
+echo "#### Machine-Readable Output" >> "$README_FILE"
+echo "" >> "$README_FILE"
+
+run_command "Newline-delimited JSON records" "cargo run -- --format ndjson --complexity --top 3 src"
+echo "> [!IMPORTANT]" >> "$README_FILE"
+echo "> Emits one JSON record per file plus a totals record. Use \`--format json\` for a single array. \`--dirs\` and \`--summary\` emit \`dir\` and \`extension\` records, or \`language\` records for \`--loc --summary\`." >> "$README_FILE"
+echo "" >> "$README_FILE"
+
+run_command "Spreadsheet-friendly CSV" "cargo run -- --format csv --dirs --size src"
+echo "> [!IMPORTANT]" >> "$README_FILE"
+echo "> Writes a header row and one row per file, directory, extension or language. Paths containing commas, quotes or newlines are quoted. Use \`--format tsv\` for tab-separated output." >> "$README_FILE"
+echo "" >> "$README_FILE"
+
 echo "### Real-Time Monitoring & Watch Mode" >> "$README_FILE"
 echo "" >> "$README_FILE"
 
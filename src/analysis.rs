@@ -1,107 +1,220 @@
-use crate::file_utils::is_binary;
+use crate::file_utils::SourceFile;
+use crate::lexer::{Language, Line};
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::path::{Path, PathBuf};
 
-pub fn calculate_complexity(path: &Path) -> Result<usize, std::io::Error> {
-    if is_binary(path)? {
-        return Ok(0);
+/// Cyclomatic complexity of the whole file: one plus every decision point, or
+/// zero for languages without decision rules.
+pub fn calculate_complexity(source: &SourceFile) -> usize {
+    if source.binary {
+        return 0;
     }
+    let Some(rules) = decision_rules(source.language()) else {
+        return 0;
+    };
 
-    let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("");
-
-    match ext {
-        "rs" | "c" | "cpp" | "cc" | "cxx" | "h" | "hpp" | "java" | "js" | "ts" | "py" | "go"
-        | "php" => calculate_code_complexity(path),
-        _ => Ok(0),
-    }
+    // Keywords in comments and strings don't branch
+    1 + source
+        .lines()
+        .iter()
+        .map(|line| count_decision_points(&line.logic(), rules))
+        .sum::<usize>()
 }
 
-fn calculate_code_complexity(path: &Path) -> Result<usize, std::io::Error> {
-    let file = File::open(path)?;
-    let reader = BufReader::new(file);
-
-    let mut complexity = 1;
-    let mut in_comment = false;
-    let mut in_string = false;
-    let mut escape_next = false;
-
-    for line in reader.lines() {
-        let line = line?;
-        let line = line.trim();
+/// What adds a path through the code of one language.
+pub(crate) struct DecisionRules {
+    /// Conditions, loops and the arms of `switch`, `select` and `try`.
+    keywords: &'static [&'static str],
+    /// Short-circuit operators, which branch when they follow an operand.
+    operators: &'static [&'static str],
+    /// Whether `?` starts a conditional expression rather than, say, Rust's
+    /// error propagation.
+    ternary: bool,
+    /// Whether `=>` ends a `match` arm. The `_` catch-all arm isn't a branch,
+    /// like `default` isn't.
+    match_arms: bool,
+    /// Whether `for` also appears in types, as in Rust's `impl Trait for T` and
+    /// `for<'a>`, where it isn't a loop.
+    type_for: bool,
+    /// Whether `T?` is also a nullable type, as in C# and Swift, so a `?` only
+    /// starts a conditional when its `:` follows on the same line.
+    nullable_types: bool,
+}
 
-        if line.is_empty() {
-            continue;
-        }
+const C_LIKE_DECISIONS: DecisionRules = DecisionRules {
+    keywords: &["if", "while", "for", "case", "catch"],
+    operators: &["&&", "||"],
+    ternary: true,
+    match_arms: false,
+    type_for: false,
+    nullable_types: false,
+};
+
+const JAVASCRIPT_DECISIONS: DecisionRules = DecisionRules {
+    operators: &["&&", "||", "??"],
+    ..C_LIKE_DECISIONS
+};
+
+const PHP_DECISIONS: DecisionRules = DecisionRules {
+    keywords: &["if", "elseif", "while", "for", "foreach", "case", "catch"],
+    operators: &["&&", "||", "??", "?:", "and", "or"],
+    ..C_LIKE_DECISIONS
+};
+
+const CSHARP_DECISIONS: DecisionRules = DecisionRules {
+    keywords: &["if", "while", "for", "foreach", "case", "catch"],
+    operators: &["&&", "||", "??"],
+    nullable_types: true,
+    ..C_LIKE_DECISIONS
+};
+
+const KOTLIN_DECISIONS: DecisionRules = DecisionRules {
+    keywords: &["if", "while", "for", "when", "catch"],
+    // `?:` is the Elvis operator; Kotlin has no `? :` conditional
+    operators: &["&&", "||", "?:"],
+    ternary: false,
+    ..C_LIKE_DECISIONS
+};
+
+const SWIFT_DECISIONS: DecisionRules = DecisionRules {
+    keywords: &["if", "guard", "while", "for", "case", "catch"],
+    operators: &["&&", "||", "??"],
+    nullable_types: true,
+    ..C_LIKE_DECISIONS
+};
+
+const SCALA_DECISIONS: DecisionRules = DecisionRules {
+    keywords: &["if", "while", "for", "case", "catch"],
+    ternary: false,
+    ..C_LIKE_DECISIONS
+};
+
+const GO_DECISIONS: DecisionRules = DecisionRules {
+    // `case` covers the arms of both `switch` and `select`
+    keywords: &["if", "for", "case"],
+    operators: &["&&", "||"],
+    ternary: false,
+    match_arms: false,
+    type_for: false,
+    nullable_types: false,
+};
+
+const RUST_DECISIONS: DecisionRules = DecisionRules {
+    keywords: &["if", "while", "for"],
+    operators: &["&&", "||"],
+    ternary: false,
+    match_arms: true,
+    type_for: true,
+    nullable_types: false,
+};
+
+const PYTHON_DECISIONS: DecisionRules = DecisionRules {
+    keywords: &["if", "elif", "while", "for", "except"],
+    operators: &["and", "or"],
+    ternary: false,
+    match_arms: false,
+    type_for: false,
+    nullable_types: false,
+};
+
+/// Decision rules for the languages complexity is measured in.
+pub(crate) fn decision_rules(language: Option<&Language>) -> Option<&'static DecisionRules> {
+    Some(match language?.name {
+        "Rust" => &RUST_DECISIONS,
+        "C" | "C++" | "Java" => &C_LIKE_DECISIONS,
+        "C#" => &CSHARP_DECISIONS,
+        "Kotlin" => &KOTLIN_DECISIONS,
+        "Swift" => &SWIFT_DECISIONS,
+        "Scala" => &SCALA_DECISIONS,
+        "JavaScript" | "TypeScript" => &JAVASCRIPT_DECISIONS,
+        "PHP" => &PHP_DECISIONS,
+        "Go" => &GO_DECISIONS,
+        "Python" => &PYTHON_DECISIONS,
+        _ => return None,
+    })
+}
 
-        let mut chars = line.chars().peekable();
-        while let Some(ch) = chars.next() {
-            if escape_next {
-                escape_next = false;
-                continue;
+/// Operators the tokenizer keeps whole, so `?.` isn't taken for a ternary and
+/// `=>` for a comparison.
+const COMPOUND_OPERATORS: &[&str] = &["&&", "||", "??", "?.", "?:", "=>", "->", "::"];
+
+/// Words after which `&&`, `||` and `?` can't be binary operators, e.g. Rust's
+/// `move || …` closures and Swift's `try?` and `as?`.
+const PREFIX_WORDS: &[&str] = &[
+    "return", "move", "in", "yield", "if", "while", "match", "else", "case", "and", "or", "not",
+    "try", "as",
+];
+
+/// Branches taken in code with its literals already blanked: each condition,
+/// loop, arm and short-circuit operator counts once.
+pub(crate) fn count_decision_points(logic: &str, rules: &DecisionRules) -> usize {
+    let tokens = tokenize(logic);
+    let mut in_impl = false;
+    let mut count = 0;
+    for (i, &token) in tokens.iter().enumerate() {
+        let previous = i.checked_sub(1).map(|i| tokens[i]);
+        let next = tokens.get(i + 1).copied();
+        let after_operand = previous.is_some_and(is_operand);
+        let decision = match token {
+            "impl" => {
+                in_impl = true;
+                false
             }
-
-            match ch {
-                '\\' if in_string => escape_next = true,
-                '"' | '\'' if !in_comment => in_string = !in_string,
-                '/' if !in_string && !in_comment => {
-                    if chars.peek() == Some(&'/') {
-                        break;
-                    } else if chars.peek() == Some(&'*') {
-                        in_comment = true;
-                        chars.next();
-                    }
-                }
-                '*' if in_comment => {
-                    if chars.peek() == Some(&'/') {
-                        in_comment = false;
-                        chars.next();
-                    }
-                }
-                _ if !in_string && !in_comment => {
-                    if is_complexity_keyword(line, ch) {
-                        complexity += 1;
-                    }
-                }
-                _ => {}
+            "for" if rules.type_for => !in_impl && next != Some("<"),
+            // Not a wildcard (`<?>`), an optional parameter (`x?,`), a nullable
+            // type or PHP's `?->`
+            "?" => {
+                rules.ternary
+                    && after_operand
+                    && !matches!(next, None | Some(">" | "," | ")" | "]" | "=" | ";" | "->"))
+                    && (!rules.nullable_types || tokens[i + 1..].contains(&":"))
             }
-        }
+            "=>" => rules.match_arms && previous != Some("_"),
+            // Scala's catch-all arm, like `default`
+            "case" if next == Some("_") => false,
+            _ if rules.keywords.contains(&token) => true,
+            _ if rules.operators.contains(&token) => after_operand,
+            _ => false,
+        };
+        count += usize::from(decision);
     }
-
-    Ok(complexity)
+    count
 }
 
-fn is_complexity_keyword(line: &str, _ch: char) -> bool {
-    let complexity_patterns = [
-        "if ",
-        "else if",
-        "while ",
-        "for ",
-        "switch ",
-        "case ",
-        "catch ",
-        "&&",
-        "||",
-        "?",
-        "break ",
-        "continue ",
-        "return ",
-        "throw ",
-    ];
-
-    complexity_patterns
-        .iter()
-        .any(|pattern| line.contains(pattern))
+/// Words, compound operators and single punctuation characters.
+fn tokenize(logic: &str) -> Vec<&str> {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+    let mut tokens = Vec::new();
+    let mut rest = logic.trim_start();
+    while let Some(first) = rest.chars().next() {
+        let len = if is_word(first) {
+            rest.find(|c: char| !is_word(c)).unwrap_or(rest.len())
+        } else if let Some(op) = COMPOUND_OPERATORS.iter().find(|op| rest.starts_with(**op)) {
+            op.len()
+        } else {
+            first.len_utf8()
+        };
+        tokens.push(&rest[..len]);
+        rest = rest[len..].trim_start();
+    }
+    tokens
 }
 
-pub fn calculate_code_density(path: &Path) -> Result<usize, std::io::Error> {
-    if is_binary(path)? {
-        return Ok(0);
+/// Whether `token` can end an operand, so an operator after it is binary.
+fn is_operand(token: &str) -> bool {
+    match token {
+        ")" | "]" | "\"" | "'" | "?" => true,
+        _ => {
+            token.starts_with(|c: char| c.is_alphanumeric() || c == '_' || c == '$')
+                && !PREFIX_WORDS.contains(&token)
+        }
     }
+}
 
-    let file = File::open(path)?;
-    let reader = BufReader::new(file);
+pub fn calculate_code_density(source: &SourceFile) -> usize {
+    if source.binary {
+        return 0;
+    }
 
     let mut total_chars = 0;
     let mut code_lines = 0;
@@ -109,18 +222,17 @@ pub fn calculate_code_density(path: &Path) -> Result<usize, std::io::Error> {
     let mut max_depth = 0;
     let mut dense_lines = 0;
 
-    for line in reader.lines() {
-        let line = line?;
-        let trimmed = line.trim();
-
-        if trimmed.is_empty() || trimmed.starts_with("//") || trimmed.starts_with('#') {
+    for line in source.lines() {
+        if !line.kind().has_code() {
             continue;
         }
+        let code = line.code();
+        let trimmed = code.trim();
 
         code_lines += 1;
         total_chars += trimmed.len();
 
-        let depth_change = count_nesting_change(trimmed);
+        let depth_change = count_nesting_change(&line.logic());
         nested_depth = (nested_depth as i32 + depth_change).max(0) as usize;
         max_depth = max_depth.max(nested_depth);
 
@@ -131,36 +243,23 @@ pub fn calculate_code_density(path: &Path) -> Result<usize, std::io::Error> {
     }
 
     if code_lines == 0 {
-        return Ok(0);
+        return 0;
     }
 
     let avg_line_length = total_chars / code_lines;
-    let density_score = (avg_line_length * max_depth * dense_lines) / code_lines.max(1);
-
-    Ok(density_score)
+    (avg_line_length * max_depth * dense_lines) / code_lines.max(1)
 }
 
-fn count_nesting_change(line: &str) -> i32 {
-    let mut change = 0;
-    let mut in_string = false;
-    let mut escape_next = false;
-
-    for ch in line.chars() {
-        if escape_next {
-            escape_next = false;
-            continue;
-        }
-
-        match ch {
-            '\\' if in_string => escape_next = true,
-            '"' | '\'' => in_string = !in_string,
-            '{' | '(' | '[' if !in_string => change += 1,
-            '}' | ')' | ']' if !in_string => change -= 1,
-            _ => {}
-        }
-    }
-
-    change
+/// Brackets opened minus closed on a line with its literals already blanked.
+fn count_nesting_change(logic: &str) -> i32 {
+    logic
+        .chars()
+        .map(|ch| match ch {
+            '{' | '(' | '[' => 1,
+            '}' | ')' | ']' => -1,
+            _ => 0,
+        })
+        .sum()
 }
 
 fn calculate_line_density(line: &str) -> usize {
@@ -194,21 +293,16 @@ pub struct EmojiAnalysis {
     pub most_common: String,
 }
 
-pub fn analyze_emojis(path: &Path) -> Result<EmojiAnalysis, std::io::Error> {
-    if is_binary(path)? {
-        return Ok(EmojiAnalysis::default());
+pub fn analyze_emojis(source: &SourceFile) -> EmojiAnalysis {
+    if source.binary {
+        return EmojiAnalysis::default();
     }
 
-    let file = File::open(path)?;
-    let reader = BufReader::new(file);
     let mut emoji_counts: HashMap<char, usize> = HashMap::new();
 
-    for line in reader.lines() {
-        let line = line?;
-        for c in line.chars() {
-            if is_emoji(c) {
-                *emoji_counts.entry(c).or_insert(0) += 1;
-            }
+    for c in source.text.chars() {
+        if is_emoji(c) {
+            *emoji_counts.entry(c).or_insert(0) += 1;
         }
     }
 
@@ -219,11 +313,11 @@ pub fn analyze_emojis(path: &Path) -> Result<EmojiAnalysis, std::io::Error> {
         .max_by_key(|(_, count)| *count)
         .map_or_else(|| "none".to_string(), |(emoji, _)| emoji.to_string());
 
-    Ok(EmojiAnalysis {
+    EmojiAnalysis {
         total,
         unique,
         most_common,
-    })
+    }
 }
 
 fn is_emoji(c: char) -> bool {
@@ -255,75 +349,82 @@ fn is_emoji(c: char) -> bool {
     }
 }
 
-pub fn calculate_duplication_percentage(
-    target_path: &Path,
-    all_files: &[PathBuf],
-) -> Result<usize, std::io::Error> {
-    if is_binary(target_path)? {
-        return Ok(0);
-    }
+/// Content-defined chunk hashes of one file. They only depend on the file's
+/// contents, so they can be cached while the index over all files can't.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct FileChunks {
+    pub chunks: Vec<u64>,
+    /// Whether the file is long enough to get a duplication score.
+    pub scored: bool,
+}
 
-    let target_content = read_normalized_content(target_path)?;
-    if target_content.len() < 100 {
-        return Ok(0);
-    }
+impl FileChunks {
+    pub fn of(source: &SourceFile) -> Self {
+        if source.binary {
+            return FileChunks::default();
+        }
 
-    let target_chunks = extract_chunks(&target_content);
-    if target_chunks.is_empty() {
-        return Ok(0);
+        let content = normalize_content(&source.lines());
+        FileChunks {
+            chunks: extract_chunks(&content),
+            scored: content.len() >= 100,
+        }
     }
+}
 
-    let mut all_chunks = HashSet::new();
-    for other_path in all_files {
-        if other_path == target_path || is_binary(other_path).unwrap_or(true) {
-            continue;
-        }
+/// Content-defined chunks of every analyzed file, used to score duplication without
+/// re-reading the rest of the tree for each file.
+pub struct DuplicationIndex {
+    chunk_files: HashMap<u64, usize>,
+}
 
-        if let Ok(other_content) = read_normalized_content(other_path) {
-            let other_chunks = extract_chunks(&other_content);
-            all_chunks.extend(other_chunks);
+impl DuplicationIndex {
+    pub fn build<'a>(files: impl Iterator<Item = &'a FileChunks>) -> Self {
+        let mut chunk_files = HashMap::new();
+        for file in files {
+            let unique: HashSet<u64> = file.chunks.iter().copied().collect();
+            for chunk in unique {
+                *chunk_files.entry(chunk).or_insert(0) += 1;
+            }
         }
+
+        DuplicationIndex { chunk_files }
     }
 
-    let duplicate_chunks = target_chunks
-        .iter()
-        .filter(|chunk| all_chunks.contains(*chunk))
-        .count();
+    pub fn duplication_percentage(&self, file: &FileChunks) -> usize {
+        if !file.scored || file.chunks.is_empty() {
+            return 0;
+        }
 
-    let duplication_percentage = if target_chunks.is_empty() {
-        0
-    } else {
-        (duplicate_chunks * 100) / target_chunks.len()
-    };
+        // Every chunk is counted once for the target itself, so anything above one
+        // means another file shares it.
+        let duplicate_chunks = file
+            .chunks
+            .iter()
+            .filter(|chunk| self.chunk_files.get(*chunk).copied().unwrap_or(0) > 1)
+            .count();
 
-    Ok(duplication_percentage.min(100))
+        ((duplicate_chunks * 100) / file.chunks.len()).min(100)
+    }
 }
 
-pub fn read_normalized_content(path: &Path) -> Result<String, std::io::Error> {
-    let file = File::open(path)?;
-    let reader = BufReader::new(file);
-
+/// The code of `lines` without comments, whitespace or case, so copies that only
+/// differ in those still match.
+pub fn normalize_content(lines: &[Line]) -> String {
     let mut normalized = String::new();
-    for line in reader.lines() {
-        let line = line?;
-        let cleaned = normalize_line(&line);
+    for line in lines {
+        let cleaned = normalize_line(&line.code());
         if !cleaned.is_empty() {
             normalized.push_str(&cleaned);
             normalized.push('\n');
         }
     }
 
-    Ok(normalized)
+    normalized
 }
 
 fn normalize_line(line: &str) -> String {
-    line.trim()
-        .replace([' ', '\t'], "")
-        .replace("//", "")
-        .replace("/*", "")
-        .replace("*/", "")
-        .replace('#', "")
-        .to_lowercase()
+    line.trim().replace([' ', '\t'], "").to_lowercase()
 }
 
 fn extract_chunks(content: &str) -> Vec<u64> {
@@ -341,8 +442,8 @@ fn extract_chunks(content: &str) -> Vec<u64> {
     let modulus = 1_000_000_007u64;
 
     // Calculate initial hash
-    for i in 0..window_size {
-        rolling_hash = (rolling_hash * base + u64::from(bytes[i])) % modulus;
+    for &byte in &bytes[..window_size] {
+        rolling_hash = (rolling_hash * base + u64::from(byte)) % modulus;
     }
 
     let mut boundaries = Vec::new();
@@ -406,26 +507,23 @@ fn simple_hash(bytes: &[u8]) -> u64 {
     hash
 }
 
-pub fn calculate_max_indent_level(path: &Path) -> Result<usize, std::io::Error> {
-    if is_binary(path)? {
-        return Ok(0);
+pub fn calculate_max_indent_level(source: &SourceFile) -> usize {
+    if source.binary {
+        return 0;
     }
 
-    let file = File::open(path)?;
-    let reader = BufReader::new(file);
     let mut max_indent = 0;
 
-    for line in reader.lines() {
-        let line = line?;
+    for line in source.text.lines() {
         if line.trim().is_empty() {
             continue;
         }
 
-        let indent_level = calculate_line_indent(&line);
+        let indent_level = calculate_line_indent(line);
         max_indent = max_indent.max(indent_level);
     }
 
-    Ok(max_indent)
+    max_indent
 }
 
 fn calculate_line_indent(line: &str) -> usize {
@@ -0,0 +1,968 @@
+use crate::analysis::{DuplicationIndex, FileChunks};
+use crate::cache::{CACHE_DIR_NAME, CachedFile, FileStamp, MetricCache, content_hash};
+use crate::file_utils::{SourceFile, is_noise_file, should_include};
+use crate::functions::Function;
+use crate::git::{
+    AuthorRules, ChurnKind, GitHistory, HistoryRange, get_primary_author, is_bare_repository,
+    read_blobs, tree_files,
+};
+use crate::metrics::{
+    Churn, Code, Complexity, Hotspots, Lines, Metric, MetricContext, OTHER_LANGUAGE, find_metric,
+};
+use crate::output::Extras;
+use crate::score::ScoreFormula;
+use ignore::WalkBuilder;
+use rayon::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+
+/// One analyzed file, directory or extension with a value per selected metric, in
+/// metric order.
+#[derive(Clone, Debug)]
+pub struct Row {
+    pub path: PathBuf,
+    pub values: Vec<usize>,
+    /// Primary author, or a comma-separated list of authors for directory rows.
+    pub author: String,
+    pub extra_info: Vec<String>,
+    pub extras: Vec<Extras>,
+    pub files: usize,
+    /// Earlier names of the file in git history, when requested.
+    pub former_paths: Vec<String>,
+}
+
+/// Ranked, filtered rows plus the totals row.
+pub struct Report {
+    pub metrics: Vec<&'static dyn Metric>,
+    pub rows: Vec<Row>,
+    pub totals: Vec<usize>,
+    pub file_count: usize,
+    /// Whether `rows` are directory aggregates rather than files.
+    pub dirs: bool,
+    /// Column of the metric rows are ranked by.
+    pub rank: usize,
+}
+
+impl Report {
+    /// Value of the named metric for `row`, if that metric was selected.
+    pub fn value(&self, row: &Row, metric: &str) -> Option<usize> {
+        self.column(metric).map(|i| row.values[i])
+    }
+
+    pub fn total(&self, metric: &str) -> Option<usize> {
+        self.column(metric).map(|i| self.totals[i])
+    }
+
+    fn column(&self, metric: &str) -> Option<usize> {
+        self.metrics.iter().position(|m| m.name() == metric)
+    }
+
+    /// Rolls the rows up by file extension, worst first.
+    pub fn by_extension(&self) -> Vec<Row> {
+        self.grouped(|row| {
+            row.path
+                .extension()
+                .and_then(|s| s.to_str())
+                .unwrap_or("no_ext")
+                .to_string()
+        })
+    }
+
+    /// Rolls the rows up by the language `code` detected, worst first, or
+    /// `None` if `code` isn't among the metrics.
+    pub fn by_language(&self) -> Option<Vec<Row>> {
+        let column = self.column(Code.name())?;
+        Some(self.grouped(|row| {
+            row.extras[column]
+                .get("language")
+                .and_then(|language| language.as_str())
+                .unwrap_or(OTHER_LANGUAGE)
+                .to_string()
+        }))
+    }
+
+    /// Rows summed up by `key`, each named after its group, worst first by the
+    /// ranked metric.
+    fn grouped(&self, key: impl Fn(&Row) -> String) -> Vec<Row> {
+        let mut groups: HashMap<String, Vec<Vec<usize>>> = HashMap::new();
+        for row in &self.rows {
+            groups.entry(key(row)).or_default().push(row.values.clone());
+        }
+
+        let mut sorted: Vec<Row> = groups
+            .into_iter()
+            .map(|(name, file_values)| Row {
+                path: PathBuf::from(name),
+                values: aggregate_values(&self.metrics, file_values.iter()),
+                author: String::new(),
+                extra_info: vec![String::new(); self.metrics.len()],
+                extras: vec![Extras::new(); self.metrics.len()],
+                files: file_values.len(),
+                former_paths: Vec::new(),
+            })
+            .collect();
+        let direction = self.metrics[self.rank].direction();
+        sorted.sort_by(|a, b| direction.worst_first(a.values[self.rank], b.values[self.rank]));
+        sorted
+    }
+}
+
+/// Configures and runs an analysis over a directory tree.
+///
+/// ```no_run
+/// use mad_useful::{Analyzer, metrics::find_metric};
+///
+/// let report = Analyzer::new("src")
+///     .metric(find_metric("complexity").unwrap())
+///     .exclude(glob::Pattern::new("*.lock").unwrap())
+///     .top(10)
+///     .run();
+/// for row in &report.rows {
+///     println!("{} {}", row.values[0], row.path.display());
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Analyzer {
+    path: PathBuf,
+    include: Vec<glob::Pattern>,
+    exclude: Vec<glob::Pattern>,
+    no_noise: bool,
+    metrics: Vec<&'static dyn Metric>,
+    /// Ranks rows instead of the first selected metric.
+    rank_by: Option<&'static dyn Metric>,
+    /// Computed for quality gates after the selected metrics, but never shown or
+    /// ranked.
+    gate_metrics: Vec<&'static dyn Metric>,
+    days: u32,
+    churn: ChurnKind,
+    score: Option<ScoreFormula>,
+    authors: bool,
+    former_paths: bool,
+    author_filter: Option<String>,
+    dirs: bool,
+    depth: Option<usize>,
+    top: Option<usize>,
+    skip: Option<usize>,
+    min_value: Option<usize>,
+    threshold: Option<u8>,
+    cache_dir: Option<PathBuf>,
+    range: HistoryRange,
+    author_rules: AuthorRules,
+    /// Read on first use and shared by every git metric and author lookup.
+    history: OnceLock<Result<Arc<GitHistory>, String>>,
+    /// Revision whose tree is analyzed instead of the working directory.
+    tree: Option<String>,
+    /// Files of the analyzed tree and their blobs, listed on first use. `None`
+    /// when analyzing the working directory.
+    tree_files: OnceLock<Result<Option<TreeFiles>, String>>,
+}
+
+/// Paths of the files in an analyzed tree with their blob ids.
+type TreeFiles = Vec<(PathBuf, String)>;
+
+/// A file's contents (if still needed) and everything computed from them alone.
+struct PreparedFile {
+    source: Option<SourceFile>,
+    cached: CachedFile,
+    /// Set when the cache should be updated for this file.
+    cache_key: Option<(FileStamp, u64)>,
+}
+
+impl Analyzer {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Analyzer {
+            path: path.into(),
+            include: Vec::new(),
+            exclude: Vec::new(),
+            no_noise: false,
+            metrics: Vec::new(),
+            rank_by: None,
+            gate_metrics: Vec::new(),
+            days: 90,
+            churn: ChurnKind::Commits,
+            score: None,
+            authors: false,
+            former_paths: false,
+            author_filter: None,
+            dirs: false,
+            depth: None,
+            top: None,
+            skip: None,
+            min_value: None,
+            threshold: None,
+            cache_dir: None,
+            range: HistoryRange::default(),
+            author_rules: AuthorRules::default(),
+            history: OnceLock::new(),
+            tree: None,
+            tree_files: OnceLock::new(),
+        }
+    }
+
+    /// Only analyze files matching this glob. May be given several times.
+    pub fn include(mut self, pattern: glob::Pattern) -> Self {
+        self.include.push(pattern);
+        self
+    }
+
+    /// Skip files matching this glob. May be given several times.
+    pub fn exclude(mut self, pattern: glob::Pattern) -> Self {
+        self.exclude.push(pattern);
+        self
+    }
+
+    /// Skip configs, lock files, generated files and other common noise.
+    pub fn no_noise(mut self, no_noise: bool) -> Self {
+        self.no_noise = no_noise;
+        self
+    }
+
+    /// Adds a metric. Metrics keep registry order; with none, lines are counted.
+    pub fn metric(mut self, metric: &'static dyn Metric) -> Self {
+        if !self.metrics.iter().any(|m| m.name() == metric.name()) {
+            self.metrics.push(metric);
+        }
+        self
+    }
+
+    /// Computes `metric` for quality gates without showing or ranking it.
+    pub fn gate_metric(mut self, metric: &'static dyn Metric) -> Self {
+        if !self.gate_metrics.iter().any(|m| m.name() == metric.name()) {
+            self.gate_metrics.push(metric);
+        }
+        self
+    }
+
+    /// Ranks, thresholds and limits rows by `metric`, which is also added, rather
+    /// than by the first selected metric.
+    pub fn rank_by(mut self, metric: &'static dyn Metric) -> Self {
+        self.rank_by = Some(metric);
+        self.metric(metric)
+    }
+
+    /// Number of days of history the git metrics look at.
+    pub fn days(mut self, days: u32) -> Self {
+        self.days = days;
+        self
+    }
+
+    /// How the default `hotspots` score measures churn: commits (the default) or
+    /// changed lines.
+    pub fn hotspot_churn(mut self, kind: ChurnKind) -> Self {
+        self.churn = kind;
+        self
+    }
+
+    /// Scores `hotspots` with `score` instead of complexity × churn.
+    pub fn hotspot_score(mut self, score: ScoreFormula) -> Self {
+        self.score = Some(score);
+        self
+    }
+
+    /// Only look at commits after `date` (e.g. `2024-01-01` or `6m`). With any explicit
+    /// range, churn counts every commit in it instead of the last `days` days.
+    pub fn since(mut self, date: impl Into<String>) -> Self {
+        self.range.since = Some(date.into());
+        self
+    }
+
+    /// Only look at commits before `date`.
+    pub fn until(mut self, date: impl Into<String>) -> Self {
+        self.range.until = Some(date.into());
+        self
+    }
+
+    /// Walk the history of a branch, commit or range like `main..feature` instead
+    /// of `HEAD`.
+    pub fn revisions(mut self, revisions: impl Into<String>) -> Self {
+        self.range.revisions = Some(revisions.into());
+        self
+    }
+
+    /// Analyze the files as they are in `revision`'s tree, read from the object
+    /// store, instead of the working directory. Bare repositories are analyzed at
+    /// `HEAD` unless another revision is given.
+    pub fn tree(mut self, revision: impl Into<String>) -> Self {
+        self.tree = Some(revision.into());
+        self.tree_files = OnceLock::new();
+        self
+    }
+
+    /// Look up each file's primary git author.
+    pub fn authors(mut self, authors: bool) -> Self {
+        self.authors = authors;
+        self
+    }
+
+    /// Count commits by `alias` (a name or email) as made by `identity`. Applied
+    /// after `.mailmap`.
+    pub fn author_alias(mut self, alias: &str, identity: impl Into<String>) -> Self {
+        self.author_rules.alias(alias, identity);
+        self
+    }
+
+    /// Identify authors by email rather than name.
+    pub fn authors_by_email(mut self, by_email: bool) -> Self {
+        self.author_rules.by_email = by_email;
+        self
+    }
+
+    /// Leave out commits by authors whose name or email matches `pattern`
+    /// (case-insensitive), e.g. bots. May be given several times.
+    pub fn exclude_author(mut self, pattern: glob::Pattern) -> Self {
+        self.author_rules.exclude.push(pattern);
+        self
+    }
+
+    /// Look up the names each file had earlier in its git history.
+    pub fn former_paths(mut self, former_paths: bool) -> Self {
+        self.former_paths = former_paths;
+        self
+    }
+
+    /// Keep only files whose primary author contains `name` (case-insensitive).
+    pub fn author_filter(mut self, name: impl Into<String>) -> Self {
+        self.author_filter = Some(name.into());
+        self
+    }
+
+    /// Aggregate results by directory, optionally truncated to `depth` levels below
+    /// the analyzed path.
+    pub fn dirs(mut self, depth: Option<usize>) -> Self {
+        self.dirs = true;
+        self.depth = depth;
+        self
+    }
+
+    pub fn top(mut self, n: usize) -> Self {
+        self.top = Some(n);
+        self
+    }
+
+    pub fn skip(mut self, n: usize) -> Self {
+        self.skip = Some(n);
+        self
+    }
+
+    /// Drop rows whose first metric is below `value`.
+    pub fn min_value(mut self, value: usize) -> Self {
+        self.min_value = Some(value);
+        self
+    }
+
+    /// Drop rows whose first metric is below `percent` of the largest value.
+    pub fn threshold(mut self, percent: u8) -> Self {
+        self.threshold = Some(percent.clamp(1, 100));
+        self
+    }
+
+    /// Reuse content-only results for unchanged files from a cache in `dir`, and
+    /// store new ones there.
+    pub fn cache(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.cache_dir = Some(dir.into());
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// History of the repository containing the analyzed path, if it's in one.
+    pub fn history(&self) -> Option<&GitHistory> {
+        self.load_history().ok()
+    }
+
+    /// Like [`Analyzer::history`], but says why the history is unavailable.
+    pub fn load_history(&self) -> Result<&GitHistory, String> {
+        self.history
+            .get_or_init(|| {
+                GitHistory::load(&self.path, &self.range, &self.author_rules).map(Arc::new)
+            })
+            .as_deref()
+            .map_err(Clone::clone)
+    }
+
+    /// Files of the analyzed tree with their blobs, or `None` when the working
+    /// directory is analyzed. Fails if git can't list the revision.
+    pub fn load_tree(&self) -> Result<Option<&[(PathBuf, String)]>, String> {
+        self.tree_files
+            .get_or_init(|| {
+                let revision = match &self.tree {
+                    Some(revision) => revision.as_str(),
+                    None if is_bare_repository(&self.path) => "HEAD",
+                    None => return Ok(None),
+                };
+                let files = tree_files(&self.path, revision)?
+                    .into_iter()
+                    // Hidden files are skipped, as walking the directory does
+                    .filter(|entry| !entry.path.split('/').any(|name| name.starts_with('.')))
+                    .map(|entry| {
+                        let path = if self.path.is_file() {
+                            self.path.clone()
+                        } else {
+                            self.path.join(&entry.path)
+                        };
+                        (path, entry.blob)
+                    })
+                    .collect();
+                Ok(Some(files))
+            })
+            .as_ref()
+            .map(|files| files.as_deref())
+            .map_err(Clone::clone)
+    }
+
+    /// The `hotspots` score: the configured one, or complexity × churn.
+    pub fn score(&self) -> ScoreFormula {
+        self.score.clone().unwrap_or_else(|| {
+            ScoreFormula::product(&[
+                &Complexity,
+                find_metric(self.churn.metric_name()).unwrap_or(&Churn),
+            ])
+        })
+    }
+
+    pub fn selected_metrics(&self) -> Vec<&'static dyn Metric> {
+        if self.metrics.is_empty() {
+            vec![&Lines]
+        } else {
+            let mut metrics = self.metrics.clone();
+            metrics.sort_by_key(|metric| crate::metrics::registry_position(metric.name()));
+            metrics
+        }
+    }
+
+    /// The selected metrics followed by the gate metrics that aren't selected:
+    /// the columns of the rows [`Analyzer::analyze`] returns.
+    pub fn computed_metrics(&self) -> Vec<&'static dyn Metric> {
+        let mut metrics = self.selected_metrics();
+        for metric in &self.gate_metrics {
+            if !metrics.iter().any(|m| m.name() == metric.name()) {
+                metrics.push(*metric);
+            }
+        }
+        metrics
+    }
+
+    /// Walks the tree, analyzes every file and builds the report.
+    pub fn run(&self) -> Report {
+        let files = self.collect_files();
+        let rows = self.analyze(&files);
+        self.report(rows)
+    }
+
+    /// Files under the analyzed path that pass the include/exclude/noise filters.
+    /// When analyzing a tree, a revision git can't list has no files.
+    pub fn collect_files(&self) -> Vec<PathBuf> {
+        match self.load_tree() {
+            Ok(Some(files)) => {
+                return files
+                    .iter()
+                    .map(|(path, _)| path)
+                    .filter(|path| {
+                        should_include(path, &self.include, &self.exclude)
+                            && (!self.no_noise || !is_noise_file(path))
+                    })
+                    .cloned()
+                    .collect();
+            }
+            Ok(None) => {}
+            Err(_) => return Vec::new(),
+        }
+
+        WalkBuilder::new(&self.path)
+            .build()
+            .filter_map(|result| {
+                if let Ok(entry) = result {
+                    let path = entry.path();
+                    if path.is_file()
+                        && !path.components().any(|c| c.as_os_str() == CACHE_DIR_NAME)
+                        && should_include(path, &self.include, &self.exclude)
+                        && (!self.no_noise || !is_noise_file(path))
+                    {
+                        Some(path.to_path_buf())
+                    } else {
+                        None
+                    }
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Scores every file for every metric. Each file is read at most once and the
+    /// contents are shared by all content metrics; with a cache, unchanged files
+    /// aren't read at all unless a metric needs more than their contents. When
+    /// analyzing a tree, every file's blob is read in one batch and the cache,
+    /// which is keyed by what's on disk, is left alone.
+    pub fn analyze(&self, files: &[PathBuf]) -> Vec<Row> {
+        let metrics = self.computed_metrics();
+        let score = self.score();
+        let hotspots = metrics
+            .iter()
+            .position(|metric| metric.name() == Hotspots.name());
+        // Metrics whose inputs must be ready: the selected ones and the score's factors
+        let mut inputs = metrics.clone();
+        if hotspots.is_some() {
+            inputs.extend(score.metrics());
+        }
+
+        let blobs = self.read_tree(files);
+        let cache = self
+            .cache_dir
+            .as_ref()
+            .filter(|_| blobs.is_none())
+            .map(|dir| MetricCache::load(dir, &self.path));
+
+        let prepared: Vec<PreparedFile> = files
+            .par_iter()
+            .map(|path| self.prepare(path, &inputs, &score, cache.as_ref(), blobs.as_ref()))
+            .collect();
+
+        let duplication_index = if inputs.iter().any(|metric| metric.needs_duplication_index()) {
+            Some(DuplicationIndex::build(
+                prepared
+                    .iter()
+                    .filter_map(|file| file.cached.chunks.as_ref()),
+            ))
+        } else {
+            None
+        };
+
+        let history = if inputs.iter().any(|metric| metric.needs_history()) {
+            self.history()
+        } else {
+            None
+        };
+
+        let mut rows: Vec<Row> = files
+            .par_iter()
+            .zip(&prepared)
+            .filter_map(|(path, prepared)| {
+                let ctx = MetricContext {
+                    path,
+                    source: prepared.source.as_ref(),
+                    chunks: prepared.cached.chunks.as_ref(),
+                    duplication: duplication_index.as_ref(),
+                    history,
+                    days: self.days,
+                    score: &score,
+                };
+
+                let mut values = Vec::with_capacity(metrics.len());
+                let mut extra_info = Vec::with_capacity(metrics.len());
+                let mut extras = Vec::with_capacity(metrics.len());
+                for metric in &metrics {
+                    let output = match prepared.cached.outputs.get(metric.name()) {
+                        Some(output) => output.clone(),
+                        None => metric.compute(&ctx),
+                    };
+                    values.push(output.value);
+                    extra_info.push(output.info);
+                    extras.push(output.extras);
+                }
+
+                let author = self.author_for(path)?;
+                let former_paths = if self.former_paths {
+                    self.history()
+                        .map(|history| history.former_paths(path))
+                        .unwrap_or_default()
+                } else {
+                    Vec::new()
+                };
+
+                Some(Row {
+                    path: path.clone(),
+                    values,
+                    author,
+                    extra_info,
+                    extras,
+                    files: 1,
+                    former_paths,
+                })
+            })
+            .collect();
+
+        if let Some(mut cache) = cache {
+            for (path, prepared) in files.iter().zip(prepared) {
+                if let Some((stamp, hash)) = prepared.cache_key {
+                    cache.insert(path, stamp, hash, prepared.cached);
+                }
+            }
+            // A cache that can't be written only costs speed on the next run
+            let _ = cache.save();
+        }
+
+        // norm() needs every file's factors before any file can be scored
+        if let Some(i) = hotspots
+            && score.normalizes()
+        {
+            let mut maxima: BTreeMap<String, usize> = BTreeMap::new();
+            let factors: Vec<_> = rows
+                .iter()
+                .map(|row| Hotspots::factors(&row.extras[i]))
+                .collect();
+            for (name, value) in factors.iter().flatten() {
+                let max = maxima.entry(name.clone()).or_insert(0);
+                *max = (*max).max(*value);
+            }
+            for (row, factors) in rows.iter_mut().zip(&factors) {
+                let output = Hotspots::scored(&score, factors, &maxima);
+                row.values[i] = output.value;
+                row.extra_info[i] = output.info;
+                row.extras[i] = output.extras;
+            }
+        }
+
+        rows.retain(|row| row.values.iter().any(|value| *value > 0));
+        rows
+    }
+
+    /// Scores the files under the analyzed path as they were in `commit`, like
+    /// [`Analyzer::tree`]. Git metrics still follow the configured history rather
+    /// than ending at `commit`. Fails if git can't list the commit.
+    pub fn analyze_commit(&self, commit: &str) -> Result<Vec<Row>, String> {
+        let analyzer = self.clone().tree(commit);
+        analyzer.load_tree()?;
+        Ok(analyzer.analyze(&analyzer.collect_files()))
+    }
+
+    /// Functions and methods of `files`, most complex first.
+    pub fn functions(&self, files: &[PathBuf]) -> Vec<Function> {
+        let blobs = self.read_tree(files);
+        let mut functions: Vec<Function> = files
+            .par_iter()
+            .filter_map(|path| read_source(path, blobs.as_ref()))
+            .flat_map_iter(|source| source.functions().to_vec())
+            .collect();
+        functions.sort_by(|a, b| {
+            b.complexity
+                .cmp(&a.complexity)
+                .then_with(|| a.path.cmp(&b.path))
+                .then(a.line.cmp(&b.line))
+        });
+        functions
+    }
+
+    /// Contents of `files` from their blobs when analyzing a tree, or `None` for
+    /// the working directory.
+    fn read_tree(&self, files: &[PathBuf]) -> Option<HashMap<PathBuf, Vec<u8>>> {
+        let tree = self.load_tree().ok().flatten()?;
+        let blob_of: HashMap<&Path, &str> = tree
+            .iter()
+            .map(|(path, blob)| (path.as_path(), blob.as_str()))
+            .collect();
+        let (paths, blobs): (Vec<&PathBuf>, Vec<&str>) = files
+            .iter()
+            .filter_map(|path| Some((path, *blob_of.get(path.as_path())?)))
+            .unzip();
+        // Unreadable blobs leave their files without contents, like unreadable files
+        let contents = read_blobs(&self.path, &blobs).unwrap_or_default();
+        Some(paths.into_iter().cloned().zip(contents).collect())
+    }
+
+    /// Reads `path` (from `blobs` when analyzing a tree) unless the cache already
+    /// has everything its contents are needed for, and computes the content-only
+    /// metrics and chunks the cache lacks.
+    fn prepare(
+        &self,
+        path: &Path,
+        metrics: &[&'static dyn Metric],
+        score: &ScoreFormula,
+        cache: Option<&MetricCache>,
+        blobs: Option<&HashMap<PathBuf, Vec<u8>>>,
+    ) -> PreparedFile {
+        let needs_chunks = metrics
+            .iter()
+            .any(|metric| metric.needs_duplication_index());
+        let complete = |cached: &CachedFile| {
+            metrics
+                .iter()
+                .filter(|metric| metric.cacheable())
+                .all(|metric| cached.outputs.contains_key(metric.name()))
+                && (!needs_chunks || cached.chunks.is_some())
+        };
+        let needs_source = metrics.iter().any(|metric| {
+            metric.needs_content() && !metric.cacheable() && !metric.needs_duplication_index()
+        });
+
+        let stamp = cache.and_then(|_| FileStamp::of(path));
+        let hit = cache
+            .zip(stamp)
+            .and_then(|(cache, stamp)| cache.by_stamp(path, stamp));
+        if let Some((_, cached)) = hit
+            && complete(cached)
+            && !needs_source
+        {
+            return PreparedFile {
+                source: None,
+                cached: cached.clone(),
+                cache_key: None,
+            };
+        }
+
+        let needs_content = metrics.iter().any(|metric| metric.needs_content());
+        let source = (needs_content || blobs.is_some())
+            .then(|| read_source(path, blobs))
+            .flatten();
+
+        let hash = cache.and(source.as_ref()).map(content_hash);
+        let mut cached = match (cache, hash) {
+            (Some(cache), Some(hash)) => cache.by_hash(hash).cloned().unwrap_or_default(),
+            _ => CachedFile::default(),
+        };
+        let up_to_date = complete(&cached);
+
+        if let Some(source) = &source {
+            let ctx = MetricContext {
+                path,
+                source: Some(source),
+                chunks: None,
+                duplication: None,
+                history: None,
+                days: self.days,
+                score,
+            };
+            for metric in metrics.iter().filter(|metric| metric.cacheable()) {
+                if !cached.outputs.contains_key(metric.name()) {
+                    cached
+                        .outputs
+                        .insert(metric.name().to_string(), metric.compute(&ctx));
+                }
+            }
+            if needs_chunks && cached.chunks.is_none() {
+                cached.chunks = Some(FileChunks::of(source));
+            }
+        }
+
+        // Re-store when results were added or the file was touched but not changed
+        let cache_key = match (stamp, hash) {
+            (Some(stamp), Some(hash)) if !up_to_date || hit.is_none_or(|(old, _)| old != hash) => {
+                Some((stamp, hash))
+            }
+            _ => None,
+        };
+
+        PreparedFile {
+            // A blob stays in memory anyway, and has no file on disk to stat for size
+            source: (needs_source || blobs.is_some()).then_some(source).flatten(),
+            cached,
+            cache_key,
+        }
+    }
+
+    /// Primary author when authors are looked up, or `None` if the file is
+    /// filtered out by the author filter.
+    pub fn author_for(&self, path: &Path) -> Option<String> {
+        let author = if self.authors || self.author_filter.is_some() {
+            self.history()
+                .and_then(|history| get_primary_author(history, path))
+                .unwrap_or_else(|| "unknown".to_string())
+        } else {
+            String::new()
+        };
+
+        if let Some(filter_author) = &self.author_filter
+            && !author
+                .to_lowercase()
+                .contains(&filter_author.to_lowercase())
+        {
+            return None;
+        }
+
+        Some(author)
+    }
+
+    /// Each metric's value over all of `rows`, by the metric's aggregation rule.
+    pub fn totals(&self, rows: &[Row]) -> Vec<usize> {
+        aggregate_values(&self.selected_metrics(), rows.iter().map(|row| &row.values))
+    }
+
+    /// Rolls file rows up into directory rows when aggregating by directory.
+    pub fn aggregate(&self, rows: Vec<Row>) -> Vec<Row> {
+        if self.dirs {
+            self.aggregate_dirs(&self.selected_metrics(), rows)
+        } else {
+            rows
+        }
+    }
+
+    /// Drops the gate metrics' columns from analyzed rows, and the rows that are
+    /// left without a value, so gates don't change what's reported.
+    pub fn visible(&self, mut rows: Vec<Row>) -> Vec<Row> {
+        let columns = self.selected_metrics().len();
+        for row in &mut rows {
+            row.values.truncate(columns);
+            row.extra_info.truncate(columns);
+            row.extras.truncate(columns);
+        }
+        rows.retain(|row| row.values.iter().any(|value| *value > 0));
+        rows
+    }
+
+    /// Aggregates (for directories), ranks and limits rows, and computes totals.
+    pub fn report(&self, rows: Vec<Row>) -> Report {
+        let metrics = self.selected_metrics();
+        let mut results = self.aggregate(self.visible(rows));
+
+        let has_filters = self.top.is_some()
+            || self.skip.is_some()
+            || self.min_value.is_some()
+            || self.threshold.is_some()
+            || !self.metrics.is_empty()
+            || self.dirs;
+
+        // Ranking, thresholds and limits all apply to the ranked metric, worst first
+        let rank = self.rank_column(&metrics);
+        let direction = metrics[rank].direction();
+        if has_filters {
+            results.sort_by(|a, b| direction.worst_first(a.values[rank], b.values[rank]));
+        } else {
+            results.sort_by(|a, b| a.path.cmp(&b.path));
+        }
+
+        if let Some(min_val) = self.min_value {
+            results.retain(|row| row.values[rank] >= min_val);
+        }
+
+        if let Some(threshold_pct) = self.threshold
+            && let Some(worst) = results.first().map(|row| row.values[rank])
+        {
+            results.retain(|row| direction.within(row.values[rank], worst, threshold_pct.into()));
+        }
+
+        if let Some(skip_n) = self.skip {
+            if skip_n < results.len() {
+                results = results.into_iter().skip(skip_n).collect();
+            } else {
+                results.clear();
+            }
+        }
+
+        if let Some(top_n) = self.top {
+            results.truncate(top_n);
+        }
+
+        let totals = self.totals(&results);
+        let file_count = results.len();
+
+        Report {
+            metrics,
+            rows: results,
+            totals,
+            file_count,
+            dirs: self.dirs,
+            rank,
+        }
+    }
+
+    /// Column of the metric rows are ranked by among `metrics`.
+    fn rank_column(&self, metrics: &[&'static dyn Metric]) -> usize {
+        self.rank_by
+            .and_then(|rank| metrics.iter().position(|m| m.name() == rank.name()))
+            .unwrap_or(0)
+    }
+
+    /// The directory row `path` is aggregated into, truncated to the `dirs` depth.
+    pub fn dir_of(&self, path: &Path) -> PathBuf {
+        let mut dir = path
+            .parent()
+            .unwrap_or_else(|| Path::new("."))
+            .to_path_buf();
+
+        // If depth is specified, truncate directory path to that depth relative to base
+        if let Some(target_depth) = self.depth {
+            let components: Vec<_> = dir.components().collect();
+            let relative_depth = self.path.components().count() + target_depth;
+            if components.len() > relative_depth {
+                dir = components.iter().take(relative_depth).collect();
+            }
+        }
+        dir
+    }
+
+    fn aggregate_dirs(&self, metrics: &[&'static dyn Metric], rows: Vec<Row>) -> Vec<Row> {
+        let mut dir_aggregates: HashMap<PathBuf, Vec<Row>> = HashMap::new();
+
+        for row in rows {
+            dir_aggregates
+                .entry(self.dir_of(&row.path))
+                .or_default()
+                .push(row);
+        }
+
+        dir_aggregates
+            .into_iter()
+            .map(|(dir, files)| {
+                let file_count = files.len();
+                let mut totals = aggregate_values(metrics, files.iter().map(|row| &row.values));
+                let mut authors: Vec<&str> = Vec::new();
+                for row in &files {
+                    if !row.author.is_empty() && !authors.contains(&row.author.as_str()) {
+                        authors.push(&row.author); // Collect unique authors
+                    }
+                }
+                let author_info = if authors.is_empty() {
+                    String::new()
+                } else {
+                    authors.join(",")
+                };
+                let mut extra_info = Vec::with_capacity(metrics.len());
+                let mut extras = Vec::with_capacity(metrics.len());
+                for (i, metric) in metrics.iter().enumerate() {
+                    let file_extras: Vec<&Extras> =
+                        files.iter().map(|row| &row.extras[i]).collect();
+                    let output = match metric.combine(&file_extras) {
+                        Some(output) => {
+                            totals[i] = output.value;
+                            output
+                        }
+                        None => metric.aggregate_output(totals[i]),
+                    };
+                    extra_info.push(output.info);
+                    extras.push(output.extras);
+                }
+                Row {
+                    path: dir,
+                    values: totals,
+                    author: author_info,
+                    extra_info,
+                    extras,
+                    files: file_count,
+                    former_paths: Vec::new(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// `path` from its blob when analyzing a tree, otherwise from the disk.
+fn read_source(path: &Path, blobs: Option<&HashMap<PathBuf, Vec<u8>>>) -> Option<SourceFile> {
+    match blobs {
+        Some(blobs) => blobs
+            .get(path)
+            .map(|bytes| SourceFile::from_bytes(path.to_path_buf(), bytes)),
+        None => SourceFile::read(path).ok(),
+    }
+}
+
+/// Rolls value columns up using each metric's aggregation rule.
+pub(crate) fn aggregate_values<'a>(
+    metrics: &[&'static dyn Metric],
+    rows: impl Iterator<Item = &'a Vec<usize>>,
+) -> Vec<usize> {
+    let mut columns = vec![Vec::new(); metrics.len()];
+    for values in rows {
+        for (column, value) in columns.iter_mut().zip(values) {
+            column.push(*value);
+        }
+    }
+
+    metrics
+        .iter()
+        .zip(&columns)
+        .map(|(metric, column)| metric.aggregation().apply(column))
+        .collect()
+}
@@ -1,8 +1,15 @@
-use clap::Parser;
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use mad_useful::gates::Gate;
+use mad_useful::git::ChurnKind;
+use mad_useful::score::ScoreFormula;
+use mad_useful::trend::parse_interval;
 
-#[derive(Parser)]
+#[derive(Clone, Parser)]
 #[command(author, version, about = "A fast, parallel code analysis tool for understanding codebase metrics and changes over time", long_about = None)]
 pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     // Input/Target
     #[arg(
         default_value = ".",
@@ -11,6 +18,27 @@ pub struct Args {
     )]
     pub path: String,
 
+    // Configuration
+    #[arg(
+        long,
+        env = "MADU_CONFIG",
+        help = "[CONFIG] Config file to use instead of the nearest .madu.toml"
+    )]
+    pub config: Option<String>,
+
+    #[arg(
+        long,
+        env = "MADU_PROFILE",
+        help = "[CONFIG] Apply a named profile from the config file"
+    )]
+    pub profile: Option<String>,
+
+    #[arg(
+        long,
+        help = "[CONFIG] Print the effective settings and where each one came from"
+    )]
+    pub print_config: bool,
+
     // File Filtering
     #[arg(
         long,
@@ -18,7 +46,7 @@ pub struct Args {
         value_delimiter = ',',
         help = "[FILTER] File filter - include files by glob pattern"
     )]
-    pub include: Vec<String>,
+    pub include: Vec<glob::Pattern>,
 
     #[arg(
         long,
@@ -26,7 +54,7 @@ pub struct Args {
         value_delimiter = ',',
         help = "[FILTER] File filter - exclude files by glob pattern"
     )]
-    pub exclude: Vec<String>,
+    pub exclude: Vec<glob::Pattern>,
 
     #[arg(
         long,
@@ -40,10 +68,17 @@ pub struct Args {
         long,
         short,
         env = "MADU_COMPLEXITY",
-        help = "[ANALYSIS] Calculate cyclomatic complexity - control flow complexity score"
+        help = "[ANALYSIS] Calculate cyclomatic complexity - one plus each decision point (branch, loop, case, &&, ||, ternary)"
     )]
     pub complexity: bool,
 
+    #[arg(
+        long,
+        env = "MADU_LOC",
+        help = "[ANALYSIS] Lines of code - code, comment, blank and mixed (code and comment) lines by language, like cloc (docstrings are comments)"
+    )]
+    pub loc: bool,
+
     #[arg(
         long,
         env = "MADU_DENSITY",
@@ -86,6 +121,15 @@ pub struct Args {
     )]
     pub emoji: bool,
 
+    #[arg(
+        long,
+        env = "MADU_METRIC",
+        value_delimiter = ',',
+        value_parser = clap::builder::PossibleValuesParser::new(mad_useful::metrics::metric_names()),
+        help = "[ANALYSIS] Select metrics by name - combine with or instead of the analysis flags"
+    )]
+    pub metric: Vec<String>,
+
     // Git Analysis
     #[arg(
         long,
@@ -97,10 +141,26 @@ pub struct Args {
     #[arg(
         long,
         env = "MADU_HOTSPOTS",
-        help = "[ANALYSIS] Risk assessment - complexity × churn score for refactoring priority"
+        help = "[ANALYSIS] Risk assessment - complexity × churn score (or --score) for refactoring priority"
     )]
     pub hotspots: bool,
 
+    #[arg(
+        long,
+        value_enum,
+        default_value = "commits",
+        env = "MADU_CHURN_BY",
+        help = "[MODIFIER] Churn measure for --churn and --hotspots - commits, lines added, deleted, changed, or relative (changed lines per current line, %)"
+    )]
+    pub churn_by: ChurnBy,
+
+    #[arg(
+        long,
+        env = "MADU_SCORE",
+        help = "[MODIFIER] Hotspot score - metrics and weights to rank --hotspots by, e.g. 'complexity * churn-lines' or '0.6*norm(complexity) + 0.4*norm(author-count)'"
+    )]
+    pub score: Option<ScoreFormula>,
+
     #[arg(
         long,
         env = "MADU_BLAME",
@@ -108,6 +168,60 @@ pub struct Args {
     )]
     pub blame: bool,
 
+    #[arg(
+        long,
+        env = "MADU_BLAME_LINES",
+        help = "[ANALYSIS] Code ownership - top owner's share of surviving lines (git blame) and number of authors"
+    )]
+    pub blame_lines: bool,
+
+    #[arg(
+        long,
+        env = "MADU_BUS_FACTOR",
+        help = "[ANALYSIS] Knowledge concentration - fewest authors covering over half of each directory's commits (lines with --blame-lines)"
+    )]
+    pub bus_factor: bool,
+
+    #[arg(
+        long,
+        env = "MADU_COUPLING",
+        help = "[ANALYSIS] Change coupling - pairs of files changed in the same commits, with support and confidence (--dirs for directories, --min-value for minimum support)"
+    )]
+    pub coupling: bool,
+
+    #[arg(
+        long,
+        env = "MADU_FUNCTIONS",
+        help = "[ANALYSIS] Function complexity - functions and methods ranked by cyclomatic complexity, with file:line (--min-value for minimum complexity)"
+    )]
+    pub functions: bool,
+
+    #[arg(
+        long,
+        env = "MADU_AUTHOR_EMAIL",
+        help = "[ANALYSIS] Git attribution - identify authors by email instead of name"
+    )]
+    pub author_email: bool,
+
+    #[arg(
+        long,
+        env = "MADU_EXCLUDE_AUTHOR",
+        value_delimiter = ',',
+        help = "[FILTER] Content filter - ignore commits by authors matching these globs, e.g. 'dependabot*'"
+    )]
+    pub exclude_author: Vec<glob::Pattern>,
+
+    /// Identity aliases from the config file's `[authors]` table.
+    #[arg(skip)]
+    pub author_aliases: Vec<(String, String)>,
+
+    #[arg(
+        long,
+        env = "MADU_SHOW_RENAMES",
+        help = "[ANALYSIS] Git attribution - show the names each file had earlier in its history"
+    )]
+    pub show_renames: bool,
+
     #[arg(
         long,
         env = "MADU_AGE",
@@ -144,6 +258,43 @@ pub struct Args {
     )]
     pub days: u32,
 
+    #[arg(
+        long,
+        env = "MADU_SINCE",
+        help = "[DISPLAY] Time scope - only use commits after this date (e.g. 2024-01-01, or 6m for six months ago) for every git metric"
+    )]
+    pub since: Option<String>,
+
+    #[arg(
+        long,
+        env = "MADU_UNTIL",
+        help = "[DISPLAY] Time scope - only use commits before this date for every git metric"
+    )]
+    pub until: Option<String>,
+
+    #[arg(
+        long,
+        env = "MADU_REV_RANGE",
+        conflicts_with_all = ["git_ref", "tree_ref"],
+        help = "[DISPLAY] Time scope - only use commits in a revision range, e.g. main..feature"
+    )]
+    pub rev_range: Option<String>,
+
+    #[arg(
+        long = "ref",
+        env = "MADU_REF",
+        help = "[DISPLAY] Time scope - use the history of this branch or commit instead of HEAD"
+    )]
+    pub git_ref: Option<String>,
+
+    #[arg(
+        long = "git-ref",
+        env = "MADU_GIT_REF",
+        conflicts_with = "git_ref",
+        help = "[DISPLAY] Time scope - analyze the files and history of this branch or commit instead of the working directory and HEAD, without a checkout (works in bare repositories)"
+    )]
+    pub tree_ref: Option<String>,
+
     #[arg(
         long,
         env = "MADU_AUTHOR",
@@ -176,12 +327,58 @@ pub struct Args {
     #[arg(long, value_parser = clap::value_parser!(u8).range(1..=100), env = "MADU_THRESHOLD", help = "[FILTER] Threshold - percentage-based filter (1-100%)")]
     pub threshold: Option<u8>,
 
+    // Quality Gates
+    #[arg(
+        long,
+        env = "MADU_FAIL_IF",
+        value_delimiter = ',',
+        help = "[GATE] Exit with status 3 if a rule is violated, e.g. complexity>40, duplicates>30%, total.lines>200000"
+    )]
+    pub fail_if: Vec<Gate>,
+
+    // Baselines
+    #[arg(
+        long,
+        env = "MADU_SAVE_BASELINE",
+        help = "[BASELINE] Record per-file metric values to a JSON file"
+    )]
+    pub save_baseline: Option<String>,
+
+    #[arg(
+        long,
+        env = "MADU_COMPARE_BASELINE",
+        help = "[BASELINE] Report only regressions, new files (at or above --min-value) and improvements against a saved baseline"
+    )]
+    pub compare_baseline: Option<String>,
+
+    #[arg(
+        long,
+        env = "MADU_RATCHET",
+        requires = "compare_baseline",
+        help = "[BASELINE] Exit with status 3 if any file regressed against --compare-baseline"
+    )]
+    pub ratchet: bool,
+
+    // Caching
+    #[arg(
+        long,
+        env = "MADU_NO_CACHE",
+        help = "[CACHE] Recompute every file instead of reusing results from .madu-cache/ or $XDG_CACHE_HOME/madu"
+    )]
+    pub no_cache: bool,
+
+    #[arg(
+        long,
+        help = "[CACHE] Delete cached results for this path before analyzing"
+    )]
+    pub clear_cache: bool,
+
     // Aggregation & Grouping
     #[arg(
         long,
         short,
         env = "MADU_SUMMARY",
-        help = "[MODIFIER] Aggregation - group results by file extension"
+        help = "[MODIFIER] Aggregation - group results by file extension (by language with --loc)"
     )]
     pub summary: bool,
 
@@ -207,6 +404,15 @@ pub struct Args {
     )]
     pub watch: Option<u64>,
 
+    #[arg(
+        long,
+        value_enum,
+        default_value = "text",
+        env = "MADU_FORMAT",
+        help = "[DISPLAY] Formatting - output format for results (text, json, ndjson, csv, tsv)"
+    )]
+    pub format: OutputFormat,
+
     #[arg(
         long,
         env = "MADU_NO_COLOR",
@@ -221,3 +427,109 @@ pub struct Args {
     )]
     pub max_lines: Option<usize>,
 }
+
+#[derive(Clone, Subcommand)]
+pub enum Command {
+    /// Evaluate the selected content metrics at commits sampled across history
+    Trend(TrendArgs),
+    /// Compare the selected metrics between two revisions or two directories
+    Diff(DiffArgs),
+}
+
+#[derive(Clone, clap::Args)]
+pub struct DiffArgs {
+    #[arg(help = "Revision or directory before the change")]
+    pub before: String,
+
+    #[arg(help = "Revision or directory after the change")]
+    pub after: String,
+
+    #[arg(help = "Directory or file to compare between revisions")]
+    pub path: Option<String>,
+}
+
+#[derive(Clone, clap::Args)]
+pub struct TrendArgs {
+    #[arg(
+        long,
+        default_value = "4w",
+        value_parser = parse_interval,
+        help = "[TREND] Sampling interval, e.g. 12h, 3d, 2w, 6m or 1y (sample back to --since, or to the first commit)"
+    )]
+    pub every: u64,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value = "total",
+        help = "[TREND] One series per file, per directory (honoring --depth) or for the total"
+    )]
+    pub by: TrendBy,
+
+    #[arg(help = "Directory or file to analyze")]
+    pub path: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum TrendBy {
+    Total,
+    Dir,
+    File,
+}
+
+impl Args {
+    /// The path to analyze: one given after a subcommand wins over the top-level one.
+    pub fn target_path(&self) -> &str {
+        match &self.command {
+            Some(Command::Trend(TrendArgs {
+                path: Some(path), ..
+            }))
+            | Some(Command::Diff(DiffArgs {
+                path: Some(path), ..
+            })) => path,
+            _ => &self.path,
+        }
+    }
+}
+
+/// The command line definition. Every option is global so it can also follow a
+/// subcommand, as in `madu trend --since 1y --complexity`.
+pub fn command() -> clap::Command {
+    Args::command().mut_args(|arg| {
+        if arg.is_positional() {
+            arg
+        } else {
+            arg.global(true)
+        }
+    })
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ChurnBy {
+    Commits,
+    Added,
+    Deleted,
+    Changed,
+    Relative,
+}
+
+impl From<ChurnBy> for ChurnKind {
+    fn from(churn_by: ChurnBy) -> Self {
+        match churn_by {
+            ChurnBy::Commits => ChurnKind::Commits,
+            ChurnBy::Added => ChurnKind::Added,
+            ChurnBy::Deleted => ChurnKind::Deleted,
+            ChurnBy::Changed => ChurnKind::Changed,
+            ChurnBy::Relative => ChurnKind::Relative,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    Ndjson,
+    Csv,
+    Tsv,
+}
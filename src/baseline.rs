@@ -0,0 +1,161 @@
+use crate::analyzer::Row;
+use crate::file_utils::relative_key;
+use crate::metrics::Metric;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const BASELINE_VERSION: u32 = 1;
+
+/// Per-file metric values recorded by `--save-baseline`. Paths are stored relative
+/// to the analyzed root so a baseline can be compared from another checkout.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Baseline {
+    pub version: u32,
+    /// Path → metric name → value.
+    pub files: BTreeMap<String, BTreeMap<String, usize>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    /// The value moved the metric's worse way since the baseline, e.g. up for
+    /// complexity or down for bus factor.
+    Regressed,
+    /// The file isn't in the baseline.
+    New,
+    /// The value moved the other way.
+    Improved,
+    /// The file is gone.
+    Removed,
+}
+
+/// One metric of one file that differs from the baseline.
+#[derive(Clone, Debug, Serialize)]
+pub struct Change {
+    pub kind: ChangeKind,
+    pub path: PathBuf,
+    pub metric: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before: Option<usize>,
+    pub after: usize,
+}
+
+impl Change {
+    pub fn delta(&self) -> i64 {
+        self.after as i64 - self.before.unwrap_or(0) as i64
+    }
+}
+
+impl Baseline {
+    pub fn from_rows(metrics: &[&'static dyn Metric], rows: &[Row], root: &Path) -> Self {
+        let files = rows
+            .iter()
+            .map(|row| {
+                let values = metrics
+                    .iter()
+                    .zip(&row.values)
+                    .map(|(metric, value)| (metric.name().to_string(), *value))
+                    .collect();
+                (relative_key(&row.path, root), values)
+            })
+            .collect();
+
+        Baseline {
+            version: BASELINE_VERSION,
+            files,
+        }
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let baseline: Baseline = serde_json::from_str(&fs::read_to_string(path)?)?;
+        if baseline.version != BASELINE_VERSION {
+            return Err(io::Error::other(format!(
+                "unsupported baseline version {}",
+                baseline.version
+            )));
+        }
+        Ok(baseline)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)? + "\n")
+    }
+
+    /// Differences between `rows` and the baseline for the metrics both have in
+    /// common: regressions (largest first), then new files (largest first), then
+    /// improvements (largest change first), then removed files. Each metric's
+    /// direction says which way is worse. Baseline files missing from `rows` are
+    /// reported as removed, with an `after` of zero.
+    pub fn compare(
+        &self,
+        metrics: &[&'static dyn Metric],
+        rows: &[Row],
+        root: &Path,
+    ) -> Vec<Change> {
+        let mut changes = Vec::new();
+        let mut seen = HashSet::new();
+
+        for row in rows {
+            let key = relative_key(&row.path, root);
+            let before = self.files.get(&key);
+            seen.insert(key);
+
+            for (metric, &after) in metrics.iter().zip(&row.values) {
+                let kind = match before {
+                    None if after > 0 => ChangeKind::New,
+                    None => continue,
+                    Some(values) => match values.get(metric.name()) {
+                        Some(&value) if metric.direction().worsened(value, after) => {
+                            ChangeKind::Regressed
+                        }
+                        Some(&value) if after != value => ChangeKind::Improved,
+                        _ => continue,
+                    },
+                };
+                changes.push(Change {
+                    kind,
+                    path: row.path.clone(),
+                    metric: metric.name(),
+                    before: before.and_then(|values| values.get(metric.name()).copied()),
+                    after,
+                });
+            }
+        }
+
+        for (key, values) in &self.files {
+            if seen.contains(key) {
+                continue;
+            }
+            for metric in metrics {
+                if let Some(&before) = values.get(metric.name())
+                    && before > 0
+                {
+                    changes.push(Change {
+                        kind: ChangeKind::Removed,
+                        path: root.join(key),
+                        metric: metric.name(),
+                        before: Some(before),
+                        after: 0,
+                    });
+                }
+            }
+        }
+
+        changes.sort_by(|a, b| {
+            let rank = |change: &Change| match change.kind {
+                ChangeKind::Regressed => 0,
+                ChangeKind::New => 1,
+                ChangeKind::Improved => 2,
+                ChangeKind::Removed => 3,
+            };
+            rank(a)
+                .cmp(&rank(b))
+                .then(b.delta().abs().cmp(&a.delta().abs()))
+                .then(a.path.cmp(&b.path))
+        });
+        changes
+    }
+}
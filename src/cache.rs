@@ -0,0 +1,188 @@
+use crate::analysis::FileChunks;
+use crate::file_utils::{SourceFile, relative_key};
+use crate::metrics::MetricOutput;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Directory name for a project-local cache, used when it exists in the analyzed path.
+pub const CACHE_DIR_NAME: &str = ".madu-cache";
+
+/// Content-only results for one version of a file.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CachedFile {
+    /// Metric name → output, for metrics whose results only depend on content.
+    pub outputs: BTreeMap<String, MetricOutput>,
+    pub chunks: Option<FileChunks>,
+}
+
+/// Size and modification time, checked before trusting a cached entry without
+/// reading the file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileStamp {
+    pub size: u64,
+    pub modified_nanos: u128,
+}
+
+impl FileStamp {
+    pub fn of(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+        Some(FileStamp {
+            size: metadata.len(),
+            modified_nanos: modified.as_nanos(),
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+struct PathEntry {
+    stamp: FileStamp,
+    hash: u64,
+}
+
+/// Bumped when a metric's definition changes without a new release, e.g. how
+/// complexity counts decision points, or how entries are keyed.
+const CACHE_REVISION: u32 = 6;
+
+fn cache_version() -> String {
+    format!("{}+{CACHE_REVISION}", env!("CARGO_PKG_VERSION"))
+}
+
+/// Per-file metric results for one analyzed root, stored as JSON. Entries are
+/// found by path when size and mtime match, or else by a hash of the contents so
+/// touched-but-unchanged files are still reused. The whole cache is dropped when
+/// the madu version or [`CACHE_REVISION`] changes, since metric definitions may
+/// have changed with it.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct MetricCache {
+    version: String,
+    /// Path relative to the root → stamp and content hash.
+    paths: HashMap<String, PathEntry>,
+    /// Content hash → results.
+    contents: HashMap<u64, CachedFile>,
+    #[serde(skip)]
+    root: PathBuf,
+    #[serde(skip)]
+    file: PathBuf,
+}
+
+impl MetricCache {
+    /// Loads the cache for `root` from `dir`, starting empty if there is none or it
+    /// can't be used.
+    pub fn load(dir: &Path, root: &Path) -> Self {
+        let file = cache_file(dir, root);
+        let cache = fs::read_to_string(&file)
+            .ok()
+            .and_then(|text| serde_json::from_str::<MetricCache>(&text).ok())
+            .filter(|cache| cache.version == cache_version());
+
+        MetricCache {
+            version: cache_version(),
+            root: root.to_path_buf(),
+            file,
+            ..cache.unwrap_or_default()
+        }
+    }
+
+    /// Results for `path` if its size and mtime still match the cached entry.
+    pub fn by_stamp(&self, path: &Path, stamp: FileStamp) -> Option<(u64, &CachedFile)> {
+        let entry = self.paths.get(&relative_key(path, &self.root))?;
+        if entry.stamp != stamp {
+            return None;
+        }
+        self.contents
+            .get(&entry.hash)
+            .map(|cached| (entry.hash, cached))
+    }
+
+    pub fn by_hash(&self, hash: u64) -> Option<&CachedFile> {
+        self.contents.get(&hash)
+    }
+
+    pub fn insert(&mut self, path: &Path, stamp: FileStamp, hash: u64, cached: CachedFile) {
+        self.paths
+            .insert(relative_key(path, &self.root), PathEntry { stamp, hash });
+        self.contents.insert(hash, cached);
+    }
+
+    /// Writes the cache, dropping entries for files that no longer exist and
+    /// contents no file refers to.
+    pub fn save(mut self) -> io::Result<()> {
+        let root = self.root.clone();
+        self.paths.retain(|key, _| root.join(key).exists());
+        let live: HashSet<u64> = self.paths.values().map(|entry| entry.hash).collect();
+        self.contents.retain(|hash, _| live.contains(hash));
+
+        if let Some(dir) = self.file.parent() {
+            fs::create_dir_all(dir)?;
+            if dir.file_name().is_some_and(|name| name == CACHE_DIR_NAME) {
+                let gitignore = dir.join(".gitignore");
+                if !gitignore.exists() {
+                    fs::write(gitignore, "*\n")?;
+                }
+            }
+        }
+
+        // Write then rename so an interrupted run never leaves a truncated cache
+        let temp = self.file.with_extension("tmp");
+        fs::write(&temp, serde_json::to_string(&self)?)?;
+        fs::rename(temp, &self.file)
+    }
+}
+
+/// Where the cache for `root` lives: `.madu-cache/` in the analyzed path if that
+/// directory exists, otherwise `$XDG_CACHE_HOME/madu` (or `~/.cache/madu`).
+pub fn default_cache_dir(root: &Path) -> Option<PathBuf> {
+    let local = if root.is_dir() {
+        root.join(CACHE_DIR_NAME)
+    } else {
+        root.parent()?.join(CACHE_DIR_NAME)
+    };
+    if local.is_dir() {
+        return Some(local);
+    }
+
+    let base = std::env::var_os("XDG_CACHE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+    Some(base.join("madu"))
+}
+
+/// Deletes the cache for `root` in `dir`, if there is one.
+pub fn clear_cache(dir: &Path, root: &Path) -> io::Result<()> {
+    match fs::remove_file(cache_file(dir, root)) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Hash of a file's contents as read for analysis and of the language they're
+/// lexed as, since the same bytes score differently as Python and as C.
+pub fn content_hash(source: &SourceFile) -> u64 {
+    let language = source.language().map_or("", |language| language.name);
+    let hash = fnv1a(&[language.as_bytes(), b"\0", source.text.as_bytes()]);
+    if source.binary { !hash } else { hash }
+}
+
+/// One cache file per analyzed root, so a shared cache directory can hold many.
+fn cache_file(dir: &Path, root: &Path) -> PathBuf {
+    let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    let name = format!("{:016x}.json", fnv1a(&[root.to_string_lossy().as_bytes()]));
+    dir.join(name)
+}
+
+/// 64-bit FNV-1a of `parts` in a row, stable across Rust versions unlike
+/// `DefaultHasher`.
+fn fnv1a(parts: &[&[u8]]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    for &byte in parts.iter().copied().flatten() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
@@ -0,0 +1,325 @@
+use crate::args::{self, Args};
+use clap::parser::ValueSource;
+use clap::{ArgMatches, FromArgMatches};
+use mad_useful::gates::Gate;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+pub const CONFIG_FILE_NAME: &str = ".madu.toml";
+
+/// Tables configuring features rather than flags.
+const SECTIONS: &[&str] = &["profiles", "gates", "authors"];
+
+/// Keys that only make sense on the command line.
+const CLI_ONLY_KEYS: &[&str] = &[
+    "path",
+    "config",
+    "profile",
+    "print-config",
+    "help",
+    "version",
+];
+
+/// Where an effective setting came from, highest precedence first.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Source {
+    CommandLine,
+    Environment(String),
+    Profile(String),
+    ConfigFile,
+    Default,
+}
+
+/// The parsed arguments after merging the config file and profile underneath the
+/// command line and environment.
+pub struct ResolvedArgs {
+    pub args: Args,
+    pub config_path: Option<PathBuf>,
+    pub config: toml::Table,
+    /// Matches for the command line and environment alone.
+    cli_matches: ArgMatches,
+    /// Final matches including values injected from the config file.
+    matches: ArgMatches,
+    layered: BTreeMap<String, Source>,
+}
+
+/// Parses the command line, then fills in anything not given on the command line
+/// or through `MADU_*` variables from the selected profile and the config file's
+/// top-level defaults. Exits with a usage error on invalid configuration.
+pub fn load_args() -> ResolvedArgs {
+    let cli_matches = args::command().get_matches();
+    let cli_args = Args::from_arg_matches(&cli_matches).unwrap_or_else(|e| e.exit());
+
+    let config_path = cli_args
+        .config
+        .as_ref()
+        .map(PathBuf::from)
+        .or_else(|| discover(Path::new(cli_args.target_path())));
+
+    let config = match &config_path {
+        Some(path) => read_config(path).unwrap_or_else(|message| fail(&message)),
+        None => toml::Table::new(),
+    };
+
+    // Later layers override earlier ones: file defaults, then the profile
+    let mut values: BTreeMap<String, (toml::Value, Source)> = BTreeMap::new();
+    for (key, value) in &config {
+        if SECTIONS.contains(&key.as_str()) {
+            continue;
+        }
+        values.insert(normalize_key(key), (value.clone(), Source::ConfigFile));
+    }
+
+    if let Some(profile) = &cli_args.profile {
+        let Some(path) = &config_path else {
+            fail(&format!(
+                "--profile {profile} given but no {CONFIG_FILE_NAME} was found"
+            ));
+        };
+        let settings = config
+            .get("profiles")
+            .and_then(|profiles| profiles.get(profile))
+            .and_then(|settings| settings.as_table())
+            .unwrap_or_else(|| {
+                fail(&format!(
+                    "profile '{profile}' is not defined in {}",
+                    path.display()
+                ))
+            });
+        for (key, value) in settings {
+            values.insert(
+                normalize_key(key),
+                (value.clone(), Source::Profile(profile.clone())),
+            );
+        }
+    }
+
+    let command = args::command();
+    let mut argv: Vec<OsString> = std::env::args_os().take(1).collect();
+    let mut layered = BTreeMap::new();
+
+    for (key, (value, source)) in values {
+        let Some(arg) = command
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some(key.as_str()))
+            .filter(|_| !CLI_ONLY_KEYS.contains(&key.as_str()))
+        else {
+            fail(&format!("unknown setting '{key}' in config"));
+        };
+
+        // The command line and environment always win over the config file
+        if matches!(
+            cli_matches.value_source(arg.get_id().as_str()),
+            Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+        ) {
+            continue;
+        }
+
+        if arg.get_action().takes_values() {
+            let items = match value {
+                toml::Value::Array(items) => items,
+                other => vec![other],
+            };
+            for item in items {
+                let text = match item {
+                    toml::Value::String(text) => text,
+                    other => other.to_string(),
+                };
+                argv.push(format!("--{key}={text}").into());
+            }
+        } else {
+            match value {
+                toml::Value::Boolean(true) => argv.push(format!("--{key}").into()),
+                toml::Value::Boolean(false) => {}
+                _ => fail(&format!("setting '{key}' must be true or false")),
+            }
+        }
+        layered.insert(key, source);
+    }
+
+    argv.extend(std::env::args_os().skip(1));
+    let matches = args::command()
+        .try_get_matches_from(argv)
+        .unwrap_or_else(|e| e.exit());
+    let args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+
+    ResolvedArgs {
+        args,
+        config_path,
+        config,
+        cli_matches,
+        matches,
+        layered,
+    }
+}
+
+/// Finds the nearest config file in `start` or any of its parent directories.
+pub fn discover(start: &Path) -> Option<PathBuf> {
+    let start = start.canonicalize().ok()?;
+    let start = if start.is_file() {
+        start.parent()?.to_path_buf()
+    } else {
+        start
+    };
+
+    start
+        .ancestors()
+        .map(|dir| dir.join(CONFIG_FILE_NAME))
+        .find(|candidate| candidate.is_file())
+}
+
+fn read_config(path: &Path) -> Result<toml::Table, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("could not read {}: {e}", path.display()))?;
+    content
+        .parse::<toml::Table>()
+        .map_err(|e| format!("invalid {}: {e}", path.display()))
+}
+
+/// A string or array of strings under `key` (or its snake_case spelling).
+fn string_list(table: &toml::Table, key: &str) -> Vec<String> {
+    let value = table.get(key).or_else(|| table.get(&key.replace('-', "_")));
+    match value {
+        None => Vec::new(),
+        Some(toml::Value::String(text)) => vec![text.clone()],
+        Some(toml::Value::Array(items)) => items
+            .iter()
+            .map(|item| {
+                item.as_str()
+                    .map(str::to_string)
+                    .unwrap_or_else(|| fail(&format!("'{key}' entries must be strings")))
+            })
+            .collect(),
+        Some(_) => fail(&format!("'{key}' must be a string or an array of strings")),
+    }
+}
+
+fn normalize_key(key: &str) -> String {
+    key.replace('_', "-")
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("error: {message}");
+    std::process::exit(2);
+}
+
+impl ResolvedArgs {
+    /// `alias = "identity"` pairs from the `[authors]` table, where an alias is an
+    /// author name or email.
+    pub fn author_aliases(&self) -> Vec<(String, String)> {
+        let Some(authors) = self.config.get("authors") else {
+            return Vec::new();
+        };
+        let Some(authors) = authors.as_table() else {
+            fail("authors must be a table of alias = \"identity\" pairs");
+        };
+        authors
+            .iter()
+            .map(|(alias, identity)| match identity.as_str() {
+                Some(identity) => (alias.clone(), identity.to_string()),
+                None => fail(&format!("authors.{alias} must be a string")),
+            })
+            .collect()
+    }
+
+    /// Gates from `[[gates]]` entries, each with `fail-if` rules and optional
+    /// `paths` globs, in file order.
+    pub fn config_gates(&self) -> Vec<Gate> {
+        let Some(entries) = self.config.get("gates") else {
+            return Vec::new();
+        };
+        let Some(entries) = entries.as_array() else {
+            fail("gates must be an array of tables ([[gates]])");
+        };
+
+        let mut gates = Vec::new();
+        for entry in entries {
+            let Some(entry) = entry.as_table() else {
+                fail("gates must be an array of tables ([[gates]])");
+            };
+            let paths: Vec<glob::Pattern> = string_list(entry, "paths")
+                .iter()
+                .map(|pattern| {
+                    glob::Pattern::new(pattern)
+                        .unwrap_or_else(|e| fail(&format!("invalid gate path '{pattern}': {e}")))
+                })
+                .collect();
+            for rule in string_list(entry, "fail-if") {
+                let gate: Gate = rule
+                    .parse()
+                    .unwrap_or_else(|message: String| fail(&message));
+                gates.push(gate.with_paths(paths.clone()));
+            }
+        }
+        gates
+    }
+
+    fn source_of(&self, id: &str, key: &str, env: Option<String>) -> Source {
+        match self.cli_matches.value_source(id) {
+            Some(ValueSource::CommandLine) => Source::CommandLine,
+            Some(ValueSource::EnvVariable) => Source::Environment(env.unwrap_or_default()),
+            _ => self.layered.get(key).cloned().unwrap_or(Source::Default),
+        }
+    }
+
+    /// Prints the effective settings as TOML, annotated with their source.
+    pub fn print_config(&self) {
+        println!(
+            "# precedence: command line > MADU_* environment > profile > config file > defaults"
+        );
+        match &self.config_path {
+            Some(path) => println!("# config file: {}", path.display()),
+            None => println!("# config file: none ({CONFIG_FILE_NAME} not found)"),
+        }
+        if let Some(profile) = &self.args.profile {
+            println!("# profile: {profile}");
+        }
+
+        let command = args::command();
+        for arg in command.get_arguments() {
+            let id = arg.get_id().as_str();
+            let key = arg
+                .get_long()
+                .map(str::to_string)
+                .unwrap_or_else(|| id.to_string());
+            if CLI_ONLY_KEYS.contains(&key.as_str()) && key != "path" {
+                continue;
+            }
+            let Some(raw) = self.matches.get_raw(id) else {
+                continue;
+            };
+            let values: Vec<String> = raw.map(|v| v.to_string_lossy().into_owned()).collect();
+
+            let value = if !arg.get_action().takes_values() {
+                if values.first().map(String::as_str) != Some("true") {
+                    continue;
+                }
+                "true".to_string()
+            } else if matches!(arg.get_action(), clap::ArgAction::Append) {
+                let items: Vec<String> = values.iter().map(|v| toml_value(v)).collect();
+                format!("[{}]", items.join(", "))
+            } else {
+                toml_value(&values[0])
+            };
+
+            let env = arg.get_env().map(|e| e.to_string_lossy().into_owned());
+            let source = match self.source_of(id, &key, env) {
+                Source::CommandLine => "command line".to_string(),
+                Source::Environment(var) => format!("environment ({var})"),
+                Source::Profile(name) => format!("profile '{name}'"),
+                Source::ConfigFile => "config file".to_string(),
+                Source::Default => "default".to_string(),
+            };
+            println!("{key} = {value}  # {source}");
+        }
+    }
+}
+
+fn toml_value(value: &str) -> String {
+    if value.parse::<i64>().is_ok() || value == "true" || value == "false" {
+        value.to_string()
+    } else {
+        toml::Value::String(value.to_string()).to_string()
+    }
+}
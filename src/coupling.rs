@@ -0,0 +1,86 @@
+use crate::git::{GitHistory, window_start};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::PathBuf;
+
+/// Commits touching more files than this are left out: sweeping refactors and
+/// formatting passes would otherwise couple everything with everything.
+pub const MAX_CHANGESET_SIZE: usize = 50;
+
+/// Two files (or directories) that changed in the same commits.
+#[derive(Clone, Debug, Serialize)]
+pub struct Coupling {
+    pub first: PathBuf,
+    pub second: PathBuf,
+    /// Commits that touched both.
+    pub support: usize,
+    /// Share of `first`'s commits that also touched `second`, in percent. `first` is
+    /// the side with fewer commits, so this is the stronger of the two directions.
+    pub confidence: usize,
+    pub first_commits: usize,
+    pub second_commits: usize,
+}
+
+/// Pairs of units that changed together in at least `min_support` commits of the
+/// last `days` days (or of the whole explicit range), most frequent first.
+///
+/// `units` maps each analyzed file to what it counts towards: the file itself, or
+/// its directory for directory-level coupling. Files of one unit don't couple with
+/// each other, and a commit counts once per unit however many of its files it touches.
+pub fn change_coupling(
+    history: &GitHistory,
+    units: &[(PathBuf, PathBuf)],
+    days: u32,
+    min_support: usize,
+) -> Vec<Coupling> {
+    let since = window_start(history, days);
+
+    // Commit → the units it touched
+    let mut changesets: HashMap<&str, BTreeSet<&PathBuf>> = HashMap::new();
+    for (file, unit) in units {
+        for commit in history.file_commits(file) {
+            if commit.timestamp >= since && commit.files.len() <= MAX_CHANGESET_SIZE {
+                changesets.entry(&commit.hash).or_default().insert(unit);
+            }
+        }
+    }
+
+    let mut commits: HashMap<&PathBuf, usize> = HashMap::new();
+    let mut pairs: BTreeMap<(&PathBuf, &PathBuf), usize> = BTreeMap::new();
+    for changed in changesets.values() {
+        let changed: Vec<&PathBuf> = changed.iter().copied().collect();
+        for (i, first) in changed.iter().enumerate() {
+            *commits.entry(first).or_insert(0) += 1;
+            for second in &changed[i + 1..] {
+                *pairs.entry((first, second)).or_insert(0) += 1;
+            }
+        }
+    }
+
+    let mut couplings: Vec<Coupling> = pairs
+        .into_iter()
+        .filter(|(_, support)| *support >= min_support.max(1))
+        .map(|((a, b), support)| {
+            let (first, second) = if commits[b] < commits[a] {
+                (b, a)
+            } else {
+                (a, b)
+            };
+            Coupling {
+                first: first.clone(),
+                second: second.clone(),
+                support,
+                confidence: support * 100 / commits[first],
+                first_commits: commits[first],
+                second_commits: commits[second],
+            }
+        })
+        .collect();
+    couplings.sort_by(|a, b| {
+        b.support
+            .cmp(&a.support)
+            .then(b.confidence.cmp(&a.confidence))
+            .then_with(|| (&a.first, &a.second).cmp(&(&b.first, &b.second)))
+    });
+    couplings
+}
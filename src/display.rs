@@ -1,4 +1,15 @@
-use termcolor::{Color, ColorSpec, StandardStream, WriteColor};
+use crate::args::{Args, OutputFormat};
+use mad_useful::baseline::{Change, ChangeKind};
+use mad_useful::coupling::Coupling;
+use mad_useful::functions::Function;
+use mad_useful::metrics::{Aggregation, Blank, Code, Comment, Metric};
+use mad_useful::output::{
+    Column, Extras, MetricValue, MetricValues, Record, write_delimited, write_json, write_ndjson,
+    write_table,
+};
+use mad_useful::trend::{Sample, Series, sparkline};
+use mad_useful::{Report, Row};
+use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
 
 pub fn print_colored_count(
     stdout: &mut StandardStream,
@@ -6,9 +17,10 @@ pub fn print_colored_count(
     file_count: usize,
     max_lines_per_file: usize,
     no_color: bool,
+    width: usize,
 ) {
     if no_color {
-        print!("{count:>8}");
+        print!("{count:>width$}");
     } else {
         let max_lines = if file_count == 1 {
             max_lines_per_file
@@ -23,7 +35,643 @@ pub fn print_colored_count(
         color_spec.set_fg(Some(Color::Rgb(red, green, 0)));
 
         stdout.set_color(&color_spec).unwrap();
-        print!("{count:>8}");
+        print!("{count:>width$}");
         stdout.reset().unwrap();
     }
 }
+
+/// Prints the report as the colored text table. `annotate` can append a suffix
+/// after each row's path (watch mode uses it for deltas).
+pub fn print_report(args: &Args, report: &Report, annotate: impl Fn(&Row) -> String) {
+    let mut stdout = StandardStream::stdout(ColorChoice::Auto);
+    let metrics = &report.metrics;
+    let max_lines_per_file = color_scale(args, report);
+
+    if args.summary
+        && let Some(rows) = report.by_language()
+    {
+        print_languages(&mut stdout, args, report, &rows, &max_lines_per_file);
+        return;
+    }
+
+    if metrics.len() > 1 {
+        for metric in metrics {
+            print!("{:>width$} ", metric.name(), width = column_width(*metric));
+        }
+        println!("{}", group_column(args, metrics));
+    }
+
+    if args.summary {
+        for row in report.by_extension() {
+            print_columns(
+                &mut stdout,
+                report,
+                &row.values,
+                1,
+                &max_lines_per_file,
+                args,
+            );
+            println!(" {} ({} files)", row.path.display(), row.files);
+        }
+    } else {
+        for row in &report.rows {
+            print_columns(
+                &mut stdout,
+                report,
+                &row.values,
+                1,
+                &max_lines_per_file,
+                args,
+            );
+            let mut extra_info: Vec<String> = metrics
+                .iter()
+                .zip(&row.extra_info)
+                .filter(|(metric, info)| {
+                    (metric.shows_extra_info() || args.dirs) && !info.is_empty()
+                })
+                .map(|(_, info)| info.clone())
+                .collect();
+            // A directory's file count once, after what its metrics say
+            if report.dirs
+                && (!extra_info.is_empty() || metrics.iter().any(|m| m.shows_extra_info()))
+            {
+                extra_info.push(format!("{}f", row.files));
+            }
+            let extra_info = extra_info.join(", ");
+            let path = if row.former_paths.is_empty() {
+                row.path.display().to_string()
+            } else {
+                format!(
+                    "{} (was {})",
+                    row.path.display(),
+                    row.former_paths.join(", ")
+                )
+            };
+            let change_str = annotate(row);
+            let author = &row.author;
+
+            if !extra_info.is_empty() {
+                if args.blame && !author.is_empty() {
+                    println!(" {path}{change_str} [{author}] ({extra_info})");
+                } else {
+                    println!(" {path}{change_str} ({extra_info})");
+                }
+            } else if args.blame && !author.is_empty() {
+                println!(" {path}{change_str} [{author}]");
+            } else {
+                println!(" {path}{change_str}");
+            }
+        }
+    }
+
+    print_columns(
+        &mut stdout,
+        report,
+        &report.totals,
+        report.file_count,
+        &max_lines_per_file,
+        args,
+    );
+    println!(" {}", total_label(args, metrics));
+}
+
+/// The per-language summary laid out like cloc's table: each language with its
+/// file count, then blank, comment and code lines, then any other metrics.
+fn print_languages(
+    stdout: &mut StandardStream,
+    args: &Args,
+    report: &Report,
+    rows: &[Row],
+    max_lines_per_file: &[usize],
+) {
+    let metrics = &report.metrics;
+    let order = cloc_order(metrics);
+    let label = total_label(args, metrics);
+    let width = rows
+        .iter()
+        .map(|row| row.path.as_os_str().len())
+        .chain([label.len(), "language".len()])
+        .max()
+        .unwrap_or_default();
+
+    print!("{:<width$} {:>8}", "language", "files");
+    for &i in &order {
+        print!(" {:>w$}", metrics[i].name(), w = column_width(metrics[i]));
+    }
+    println!();
+
+    let mut print_row = |name: &str, files: usize, values: &[usize], file_count: usize| {
+        print!("{name:<width$} {files:>8}");
+        for &i in &order {
+            print!(" ");
+            print_colored_count(
+                stdout,
+                values[i],
+                file_count,
+                max_lines_per_file[i],
+                args.no_color,
+                column_width(metrics[i]),
+            );
+        }
+        println!();
+    };
+    for row in rows {
+        print_row(&row.path.to_string_lossy(), row.files, &row.values, 1);
+    }
+    print_row(label, report.file_count, &report.totals, report.file_count);
+}
+
+/// Indices of `metrics` in cloc's column order: blank, comment and code lines
+/// first, the rest after them as selected.
+fn cloc_order(metrics: &[&'static dyn Metric]) -> Vec<usize> {
+    let cloc = [Blank.name(), Comment.name(), Code.name()];
+    let mut order: Vec<usize> = (0..metrics.len()).collect();
+    order.sort_by_key(|&i| {
+        cloc.iter()
+            .position(|name| *name == metrics[i].name())
+            .unwrap_or(cloc.len())
+    });
+    order
+}
+
+/// Per-metric reference values for color scaling: `--max-lines`, or the largest
+/// value shown, or the metric's default.
+fn color_scale(args: &Args, report: &Report) -> Vec<usize> {
+    report
+        .metrics
+        .iter()
+        .enumerate()
+        .map(|(i, metric)| {
+            args.max_lines.unwrap_or_else(|| {
+                report
+                    .rows
+                    .iter()
+                    .map(|row| row.values[i])
+                    .max()
+                    .unwrap_or(metric.default_max())
+            })
+        })
+        .collect()
+}
+
+/// Writes the report as JSON, NDJSON, CSV or TSV records.
+pub fn write_report(args: &Args, report: Report) {
+    let metrics = &report.metrics;
+    let mut records = Vec::new();
+
+    if args.summary
+        && let Some(rows) = report.by_language()
+    {
+        for row in rows {
+            records.push(Record::Language {
+                language: row.path.to_string_lossy().into_owned(),
+                files: row.files,
+                metrics: metric_values(metrics, &row.values, Vec::new()),
+            });
+        }
+    } else if args.summary {
+        for row in report.by_extension() {
+            records.push(Record::Extension {
+                extension: row.path.to_string_lossy().into_owned(),
+                files: row.files,
+                metrics: metric_values(metrics, &row.values, Vec::new()),
+            });
+        }
+    } else {
+        for row in report.rows {
+            let values = metric_values(metrics, &row.values, row.extras);
+            if args.dirs {
+                let authors = row
+                    .author
+                    .split(',')
+                    .filter(|name| !name.is_empty())
+                    .map(str::to_string)
+                    .collect();
+                records.push(Record::Dir {
+                    path: row.path,
+                    files: row.files,
+                    authors,
+                    metrics: values,
+                });
+            } else {
+                records.push(Record::File {
+                    path: row.path,
+                    author: (!row.author.is_empty()).then_some(row.author),
+                    former_paths: row.former_paths,
+                    metrics: values,
+                });
+            }
+        }
+    }
+
+    records.push(Record::Total {
+        count: report.file_count,
+        label: total_label(args, metrics),
+        metrics: metric_values(metrics, &report.totals, Vec::new()),
+    });
+
+    match args.format {
+        OutputFormat::Json => write_json(&records),
+        OutputFormat::Ndjson => write_ndjson(&records),
+        OutputFormat::Csv => write_delimited(&records, ',', &table_header(args, metrics)),
+        OutputFormat::Tsv => write_delimited(&records, '\t', &table_header(args, metrics)),
+        OutputFormat::Text => {}
+    }
+}
+
+/// Prints baseline differences, one line per file and metric: the change (red for
+/// regressions, green for improvements) or the value of a new file, then a tally.
+pub fn print_changes(args: &Args, changes: &[Change]) {
+    let mut stdout = StandardStream::stdout(ColorChoice::Auto);
+    let name_width = changes
+        .iter()
+        .map(|change| change.metric.len())
+        .max()
+        .unwrap_or(0);
+
+    for change in changes {
+        let (column, color) = match change.kind {
+            ChangeKind::Regressed => (signed(change.delta()), Color::Red),
+            ChangeKind::Improved | ChangeKind::Removed => (signed(change.delta()), Color::Green),
+            ChangeKind::New => (change.after.to_string(), Color::Yellow),
+        };
+        if args.no_color {
+            print!("{column:>8}");
+        } else {
+            stdout
+                .set_color(ColorSpec::new().set_fg(Some(color)))
+                .unwrap();
+            print!("{column:>8}");
+            stdout.reset().unwrap();
+        }
+
+        let detail = match (change.kind, change.before) {
+            (ChangeKind::Removed, _) => "removed".to_string(),
+            (_, Some(before)) => format!("{before} → {}", change.after),
+            (_, None) => "new".to_string(),
+        };
+        println!(
+            " {:<name_width$} {} ({detail})",
+            change.metric,
+            change.path.display()
+        );
+    }
+
+    let count = |kind| changes.iter().filter(|change| change.kind == kind).count();
+    println!(
+        "{} regressed, {} new, {} improved, {} removed",
+        count(ChangeKind::Regressed),
+        count(ChangeKind::New),
+        count(ChangeKind::Improved),
+        count(ChangeKind::Removed)
+    );
+}
+
+/// A change as `+N` or `-N`, the way deltas are shown everywhere.
+pub fn signed(delta: i64) -> String {
+    if delta > 0 {
+        format!("+{delta}")
+    } else {
+        delta.to_string()
+    }
+}
+
+/// Writes baseline differences as JSON, NDJSON, CSV or TSV records.
+pub fn write_changes(args: &Args, changes: &[Change]) {
+    let delimiter = match args.format {
+        OutputFormat::Json => return write_json(changes),
+        OutputFormat::Ndjson => return write_ndjson(changes),
+        OutputFormat::Csv => ',',
+        OutputFormat::Tsv => '\t',
+        OutputFormat::Text => return,
+    };
+
+    let rows = changes.iter().map(|change| {
+        let kind = match change.kind {
+            ChangeKind::Regressed => "regressed",
+            ChangeKind::New => "new",
+            ChangeKind::Improved => "improved",
+            ChangeKind::Removed => "removed",
+        };
+        vec![
+            kind.to_string(),
+            change.path.to_string_lossy().into_owned(),
+            change.metric.to_string(),
+            change
+                .before
+                .map(|before| before.to_string())
+                .unwrap_or_default(),
+            change.after.to_string(),
+            change.delta().to_string(),
+        ]
+    });
+    write_table(
+        &["kind", "path", "metric", "before", "after", "delta"],
+        rows,
+        delimiter,
+    );
+}
+
+/// Prints coupled pairs: shared commits, confidence (colored by strength) and the
+/// two paths, then a tally.
+pub fn print_couplings(args: &Args, couplings: &[Coupling]) {
+    let mut stdout = StandardStream::stdout(ColorChoice::Auto);
+    for coupling in couplings {
+        print!("{:>8} ", coupling.support);
+        print_colored_count(&mut stdout, coupling.confidence, 1, 100, args.no_color, 3);
+        println!(
+            "% {} ↔ {} ({} / {} commits)",
+            coupling.first.display(),
+            coupling.second.display(),
+            coupling.first_commits,
+            coupling.second_commits
+        );
+    }
+    println!("{} coupled pairs", couplings.len());
+}
+
+/// Writes coupled pairs as JSON, NDJSON, CSV or TSV records.
+pub fn write_couplings(args: &Args, couplings: &[Coupling]) {
+    let delimiter = match args.format {
+        OutputFormat::Json => return write_json(couplings),
+        OutputFormat::Ndjson => return write_ndjson(couplings),
+        OutputFormat::Csv => ',',
+        OutputFormat::Tsv => '\t',
+        OutputFormat::Text => return,
+    };
+
+    let rows = couplings.iter().map(|coupling| {
+        vec![
+            coupling.first.to_string_lossy().into_owned(),
+            coupling.second.to_string_lossy().into_owned(),
+            coupling.support.to_string(),
+            coupling.confidence.to_string(),
+            coupling.first_commits.to_string(),
+            coupling.second_commits.to_string(),
+        ]
+    });
+    write_table(
+        &[
+            "first",
+            "second",
+            "support",
+            "confidence",
+            "first_commits",
+            "second_commits",
+        ],
+        rows,
+        delimiter,
+    );
+}
+
+/// Prints functions as complexity (colored against `--max-lines` or 15), then
+/// `path:line` and the name, then a tally.
+pub fn print_functions(args: &Args, functions: &[Function]) {
+    let mut stdout = StandardStream::stdout(ColorChoice::Auto);
+    let max = args.max_lines.unwrap_or(15);
+    for function in functions {
+        print_colored_count(&mut stdout, function.complexity, 1, max, args.no_color, 8);
+        println!(
+            " {}:{} {}",
+            function.path.display(),
+            function.line,
+            function.name
+        );
+    }
+    println!("{} functions", functions.len());
+}
+
+/// Writes functions as JSON, NDJSON, CSV or TSV records.
+pub fn write_functions(args: &Args, functions: &[Function]) {
+    let delimiter = match args.format {
+        OutputFormat::Json => return write_json(functions),
+        OutputFormat::Ndjson => return write_ndjson(functions),
+        OutputFormat::Csv => ',',
+        OutputFormat::Tsv => '\t',
+        OutputFormat::Text => return,
+    };
+
+    let rows = functions.iter().map(|function| {
+        vec![
+            function.path.to_string_lossy().into_owned(),
+            function.line.to_string(),
+            function.end_line.to_string(),
+            function.name.clone(),
+            function.complexity.to_string(),
+        ]
+    });
+    write_table(
+        &["path", "line", "end_line", "name", "complexity"],
+        rows,
+        delimiter,
+    );
+}
+
+/// Prints each metric's series: the latest value, a sparkline over the samples
+/// and the change since the first sample.
+pub fn print_trend(args: &Args, samples: &[Sample], series: &[Series]) {
+    let mut stdout = StandardStream::stdout(ColorChoice::Auto);
+    let (Some(first), Some(last)) = (samples.first(), samples.last()) else {
+        return;
+    };
+
+    let mut names: Vec<&str> = Vec::new();
+    for series in series {
+        if !names.contains(&series.metric) {
+            names.push(series.metric);
+        }
+    }
+
+    for name in names {
+        println!(
+            "{name}: {} samples, {} → {}",
+            samples.len(),
+            first.date,
+            last.date
+        );
+        let metric_series: Vec<&Series> = series
+            .iter()
+            .filter(|series| series.metric == name)
+            .collect();
+        let max = args.max_lines.unwrap_or_else(|| {
+            metric_series
+                .iter()
+                .filter_map(|series| series.last())
+                .max()
+                .unwrap_or(0)
+                .max(1)
+        });
+
+        for series in metric_series {
+            let latest = series.last().unwrap_or(0);
+            print_colored_count(&mut stdout, latest, 1, max, args.no_color, 8);
+            let change = match latest as i64 - series.first().unwrap_or(0) as i64 {
+                0 => String::new(),
+                delta => format!(" ({})", signed(delta)),
+            };
+            let path = series
+                .path
+                .as_ref()
+                .map_or("total".to_string(), |path| path.display().to_string());
+            println!(" {} {path}{change}", sparkline(&series.values));
+        }
+    }
+}
+
+/// Writes trend series as JSON or NDJSON records, or as CSV/TSV with one row per
+/// series and sample.
+pub fn write_trend(args: &Args, samples: &[Sample], series: &[Series]) {
+    let records: Vec<_> = series.iter().map(|series| series.record(samples)).collect();
+    let delimiter = match args.format {
+        OutputFormat::Json => return write_json(&records),
+        OutputFormat::Ndjson => return write_ndjson(&records),
+        OutputFormat::Csv => ',',
+        OutputFormat::Tsv => '\t',
+        OutputFormat::Text => return,
+    };
+
+    let rows = records.iter().flat_map(|record| {
+        record.points.iter().filter_map(|point| {
+            Some(vec![
+                point.date.to_string(),
+                point.commit.to_string(),
+                record
+                    .path
+                    .map(|path| path.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| "total".to_string()),
+                record.metric.to_string(),
+                point.value?.to_string(),
+            ])
+        })
+    });
+    write_table(
+        &["date", "commit", "path", "metric", "value"],
+        rows,
+        delimiter,
+    );
+}
+
+fn total_label(args: &Args, metrics: &[&'static dyn Metric]) -> &'static str {
+    if args.dirs {
+        "total dirs"
+    } else if metrics.len() > 1 {
+        "total"
+    } else {
+        metrics[0].label()
+    }
+}
+
+fn column_width(metric: &dyn Metric) -> usize {
+    metric.name().len().max(8)
+}
+
+fn print_columns(
+    stdout: &mut StandardStream,
+    report: &Report,
+    values: &[usize],
+    file_count: usize,
+    max_lines_per_file: &[usize],
+    args: &Args,
+) {
+    let metrics = &report.metrics;
+    for (i, (metric, value)) in metrics.iter().zip(values).enumerate() {
+        if i > 0 {
+            print!(" ");
+        }
+        let width = if metrics.len() > 1 {
+            column_width(*metric)
+        } else {
+            8
+        };
+        // Averages and maxima stay on the per-file scale; sums grow with the file count
+        let file_count = if metric.aggregation() == Aggregation::Sum {
+            file_count
+        } else {
+            1
+        };
+        print_colored_count(
+            stdout,
+            *value,
+            file_count,
+            max_lines_per_file[i],
+            args.no_color,
+            width,
+        );
+    }
+}
+
+fn metric_values(
+    metrics: &[&'static dyn Metric],
+    values: &[usize],
+    extras: Vec<Extras>,
+) -> MetricValues {
+    let mut extras = extras.into_iter();
+    metrics
+        .iter()
+        .zip(values)
+        .map(|(metric, value)| {
+            (
+                metric.name(),
+                MetricValue {
+                    unit: metric.unit(),
+                    value: *value,
+                    extras: extras.next().unwrap_or_default(),
+                },
+            )
+        })
+        .collect()
+}
+
+/// What the rows are named by: paths, or the groups of a summary, which are
+/// languages when `code` detected them.
+fn group_column(args: &Args, metrics: &[&'static dyn Metric]) -> &'static str {
+    if !args.summary {
+        "path"
+    } else if metrics.iter().any(|metric| metric.name() == Code.name()) {
+        "language"
+    } else {
+        "extension"
+    }
+}
+
+fn table_header(args: &Args, metrics: &[&'static dyn Metric]) -> Vec<Column> {
+    let group = group_column(args, metrics);
+    if group == "language" {
+        let mut header = vec![Column::Key(group), Column::Files];
+        header.extend(
+            cloc_order(metrics)
+                .into_iter()
+                .map(|i| Column::Metric(metrics[i].name())),
+        );
+        return header;
+    }
+
+    let mut header = vec![Column::Key(group)];
+    for metric in metrics {
+        header.push(Column::Metric(metric.name()));
+        if !args.summary {
+            for key in metric.extra_columns(args.dirs) {
+                header.push(Column::Extra(metric.name(), key));
+            }
+        }
+    }
+
+    if args.summary || args.dirs {
+        header.push(Column::Files);
+    }
+    // Authors are only looked up for --blame and --author, like JSON leaves them out
+    let authors = args.blame || args.author.is_some();
+    if args.dirs {
+        if authors {
+            header.push(Column::Author("authors"));
+        }
+    } else if !args.summary {
+        if authors {
+            header.push(Column::Author("author"));
+        }
+        if args.show_renames {
+            header.push(Column::FormerPaths);
+        }
+    }
+    header
+}
@@ -1,13 +1,125 @@
-use std::fs::File;
-use std::io::{BufRead, BufReader, Read};
-use std::path::Path;
+use crate::functions::{Function, find_functions};
+use crate::lexer::{Language, Line, LineKind, LineSpans, detect_language, lex};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
-pub fn should_include(path: &Path, include: &[String], exclude: &[String]) -> bool {
+/// A file's contents, read once and shared by every content analyzer.
+pub struct SourceFile {
+    pub path: PathBuf,
+    pub text: String,
+    pub binary: bool,
+    /// Size in bytes.
+    pub size: usize,
+    /// Lexed once and shared by every caller of `lines`.
+    spans: OnceLock<Vec<LineSpans>>,
+    /// Shared by the metrics of the `--loc` breakdown.
+    line_counts: OnceLock<LineCounts>,
+    /// Shared by the per-function complexity metrics.
+    functions: OnceLock<Vec<Function>>,
+}
+
+/// Lines of a file by what they hold. `code` includes the `mixed` lines that also
+/// carry a comment, as cloc counts them.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LineCounts {
+    pub code: usize,
+    pub comment: usize,
+    pub blank: usize,
+    pub mixed: usize,
+}
+
+impl SourceFile {
+    pub fn read(path: &Path) -> Result<Self, std::io::Error> {
+        Ok(Self::from_bytes(path.to_path_buf(), &std::fs::read(path)?))
+    }
+
+    /// A file whose contents come from somewhere other than the disk, e.g. a git blob.
+    pub fn from_bytes(path: PathBuf, bytes: &[u8]) -> Self {
+        let binary = is_binary(bytes);
+        let text = if binary {
+            String::new()
+        } else {
+            String::from_utf8_lossy(bytes).into_owned()
+        };
+
+        SourceFile {
+            path,
+            text,
+            binary,
+            size: bytes.len(),
+            spans: OnceLock::new(),
+            line_counts: OnceLock::new(),
+            functions: OnceLock::new(),
+        }
+    }
+
+    pub fn extension(&self) -> &str {
+        self.path.extension().and_then(|s| s.to_str()).unwrap_or("")
+    }
+
+    pub fn language(&self) -> Option<&'static Language> {
+        detect_language(&self.path, &self.text)
+    }
+
+    /// The text split into code, comments and literals by the rules of the
+    /// file's language; every line is code in files of unknown languages.
+    pub fn lines(&self) -> Vec<Line<'_>> {
+        self.spans
+            .get_or_init(|| lex(&self.text, self.language()))
+            .iter()
+            .map(|spans| Line::of(&self.text, spans))
+            .collect()
+    }
+
+    /// Code, comment, blank and mixed lines; none for binary files.
+    pub fn line_counts(&self) -> LineCounts {
+        *self.line_counts.get_or_init(|| {
+            let mut counts = LineCounts::default();
+            if self.binary {
+                return counts;
+            }
+            for line in self.lines() {
+                match line.kind() {
+                    LineKind::Blank => counts.blank += 1,
+                    LineKind::Comment => counts.comment += 1,
+                    LineKind::Code => counts.code += 1,
+                    LineKind::Mixed => {
+                        counts.code += 1;
+                        counts.mixed += 1;
+                    }
+                }
+            }
+            counts
+        })
+    }
+
+    /// Functions and methods with their complexity; none for languages without
+    /// function detection.
+    pub fn functions(&self) -> &[Function] {
+        self.functions.get_or_init(|| find_functions(self))
+    }
+}
+
+/// Path relative to the analyzed root with `/` separators, used as a stable key in
+/// files written to disk.
+pub fn relative_key(path: &Path, root: &Path) -> String {
+    let relative = match path.strip_prefix(root) {
+        Ok(relative) if !relative.as_os_str().is_empty() => relative,
+        _ => path,
+    };
+    relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+pub fn should_include(path: &Path, include: &[glob::Pattern], exclude: &[glob::Pattern]) -> bool {
     let path_str = path.to_string_lossy();
 
     if !exclude.is_empty() {
         for pattern in exclude {
-            if glob::Pattern::new(pattern).unwrap().matches(&path_str) {
+            if pattern.matches(&path_str) {
                 return false;
             }
         }
@@ -15,7 +127,7 @@ pub fn should_include(path: &Path, include: &[String], exclude: &[String]) -> bo
 
     if !include.is_empty() {
         for pattern in include {
-            if glob::Pattern::new(pattern).unwrap().matches(&path_str) {
+            if pattern.matches(&path_str) {
                 return true;
             }
         }
@@ -25,26 +137,22 @@ pub fn should_include(path: &Path, include: &[String], exclude: &[String]) -> bo
     true
 }
 
-pub fn count_lines(path: &Path) -> Result<usize, std::io::Error> {
-    if is_binary(path)? {
-        return Ok(0);
+pub fn count_lines(source: &SourceFile) -> usize {
+    if source.binary {
+        return 0;
     }
-    let file = File::open(path)?;
-    let reader = BufReader::new(file);
-    Ok(reader.lines().count())
+    source.text.lines().count()
 }
 
-pub fn is_binary(path: &Path) -> Result<bool, std::io::Error> {
-    let mut file = File::open(path)?;
-    let mut buffer = [0; 512];
-    let bytes_read = file.read(&mut buffer)?;
+pub fn is_binary(bytes: &[u8]) -> bool {
+    let head = &bytes[..bytes.len().min(512)];
 
-    if bytes_read == 0 {
-        return Ok(false);
+    if head.is_empty() {
+        return false;
     }
 
-    let null_count = buffer[..bytes_read].iter().filter(|&&b| b == 0).count();
-    Ok(null_count > bytes_read / 100)
+    let null_count = head.iter().filter(|&&b| b == 0).count();
+    null_count > head.len() / 100
 }
 
 pub fn is_noise_file(path: &Path) -> bool {
@@ -135,21 +243,12 @@ pub fn is_noise_file(path: &Path) -> bool {
         || noise_files.iter().any(|file| filename.starts_with(file))
 }
 
-pub fn count_nonwhitespace_chars(path: &Path) -> Result<usize, std::io::Error> {
-    if is_binary(path)? {
-        return Ok(0);
-    }
-
-    let file = File::open(path)?;
-    let reader = BufReader::new(file);
-    let mut char_count = 0;
-
-    for line in reader.lines() {
-        let line = line?;
-        char_count += line.chars().filter(|&c| !c.is_whitespace()).count();
+pub fn count_nonwhitespace_chars(source: &SourceFile) -> usize {
+    if source.binary {
+        return 0;
     }
 
-    Ok(char_count)
+    source.text.chars().filter(|&c| !c.is_whitespace()).count()
 }
 
 pub fn get_file_size(path: &Path) -> Result<usize, std::io::Error> {
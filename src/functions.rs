@@ -0,0 +1,461 @@
+use crate::analysis::{DecisionRules, count_decision_points, decision_rules};
+use crate::file_utils::SourceFile;
+use crate::lexer::{Line, LineKind, Segment};
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// A function or method found in a source file.
+#[derive(Clone, Debug, Serialize)]
+pub struct Function {
+    pub path: PathBuf,
+    /// `(anonymous)` for unnamed functions and closures that get a body of their own.
+    pub name: String,
+    /// 1-based line of the header.
+    pub line: usize,
+    pub end_line: usize,
+    /// Cyclomatic complexity: one plus the decision points in the body. Nested
+    /// functions count their own decisions, not their parent.
+    pub complexity: usize,
+}
+
+const ANONYMOUS: &str = "(anonymous)";
+
+/// How a language's functions are told apart.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Syntax {
+    Rust,
+    Go,
+    /// C, C++, C# and Java: a name and parameter list followed by a body.
+    CLike,
+    /// Kotlin, Swift and Scala: a keyword (`fun`, `func`, `def`) before the
+    /// name, with the body opening on the header line.
+    Keyword(&'static str),
+    JavaScript,
+    Php,
+    Python,
+}
+
+fn syntax(source: &SourceFile) -> Option<Syntax> {
+    Some(match source.language()?.name {
+        "Rust" => Syntax::Rust,
+        "Go" => Syntax::Go,
+        "C" | "C++" | "C#" | "Java" => Syntax::CLike,
+        "Kotlin" => Syntax::Keyword("fun"),
+        "Swift" => Syntax::Keyword("func"),
+        "Scala" => Syntax::Keyword("def"),
+        "JavaScript" | "TypeScript" => Syntax::JavaScript,
+        "PHP" => Syntax::Php,
+        "Python" => Syntax::Python,
+        _ => return None,
+    })
+}
+
+/// The functions and methods of `source` in order of their headers, or none for
+/// languages without function detection.
+pub fn find_functions(source: &SourceFile) -> Vec<Function> {
+    if source.binary {
+        return Vec::new();
+    }
+    let (Some(syntax), Some(rules)) = (syntax(source), decision_rules(source.language())) else {
+        return Vec::new();
+    };
+
+    let lines = source.lines();
+    let mut functions = match syntax {
+        Syntax::Python => indented_functions(&lines, rules),
+        _ => braced_functions(&lines, syntax, rules),
+    };
+    for function in &mut functions {
+        function.path = source.path.clone();
+    }
+    functions.sort_by_key(|function| function.line);
+    functions
+}
+
+/// A function whose body is still open.
+struct Open {
+    function: Function,
+    /// Brace depth inside the body, or the header's indentation for Python.
+    level: usize,
+}
+
+fn open(name: String, line: usize, level: usize) -> Open {
+    Open {
+        function: Function {
+            path: PathBuf::new(),
+            name,
+            line,
+            end_line: line,
+            complexity: 1,
+        },
+        level,
+    }
+}
+
+/// Functions whose bodies are delimited by braces: a header, then the body from
+/// the next `{` to its matching `}`.
+fn braced_functions(lines: &[Line], syntax: Syntax, rules: &DecisionRules) -> Vec<Function> {
+    let mut functions = Vec::new();
+    let mut stack: Vec<Open> = Vec::new();
+    // A header waiting for its body, with the line it started on
+    let mut pending: Option<(String, usize)> = None;
+    let mut depth = 0;
+
+    for (i, line) in lines.iter().enumerate() {
+        let number = i + 1;
+        let logic = line.logic();
+        if let Some(name) = header(&logic, syntax) {
+            pending = Some((name, number));
+        }
+
+        // Each stretch between braces gives its decisions to the innermost
+        // function open there
+        let mut stretch = 0;
+        for (at, ch) in logic.char_indices() {
+            if !matches!(ch, '{' | '}' | ';') {
+                continue;
+            }
+            if let Some(open) = stack.last_mut() {
+                open.function.complexity += count_decision_points(&logic[stretch..at], rules);
+            }
+            stretch = at + 1;
+            match ch {
+                // Go's `struct{}` and `interface{}` are types in the signature
+                '{' if syntax == Syntax::Go
+                    && ["struct", "interface"]
+                        .iter()
+                        .any(|word| logic[..at].trim_end().ends_with(word)) =>
+                {
+                    depth += 1;
+                }
+                '{' => {
+                    depth += 1;
+                    if let Some((name, start)) = pending.take() {
+                        stack.push(open(name, start, depth));
+                    }
+                }
+                '}' => {
+                    if stack.last().is_some_and(|open| open.level == depth) {
+                        let mut open = stack.pop().unwrap();
+                        open.function.end_line = number;
+                        functions.push(open.function);
+                    }
+                    depth = depth.saturating_sub(1);
+                }
+                // A declaration without a body
+                _ => pending = None,
+            }
+        }
+        if let Some(open) = stack.last_mut() {
+            open.function.complexity += count_decision_points(&logic[stretch..], rules);
+        }
+
+        // These headers open their body on the same line, so they don't wait
+        // for one on a later line: a header without one has an expression body
+        if matches!(syntax, Syntax::JavaScript | Syntax::Go | Syntax::Keyword(_)) {
+            pending = None;
+        }
+    }
+
+    // Bodies the file never closes end with it
+    for mut open in stack {
+        open.function.end_line = lines.len();
+        functions.push(open.function);
+    }
+    functions
+}
+
+/// Python functions: a `def` and the lines indented deeper than it.
+fn indented_functions(lines: &[Line], rules: &DecisionRules) -> Vec<Function> {
+    let mut functions = Vec::new();
+    let mut stack: Vec<Open> = Vec::new();
+    let mut last_code_line = 0;
+
+    for (i, line) in lines.iter().enumerate() {
+        let number = i + 1;
+        if !line.kind().has_code() {
+            continue;
+        }
+        // Continuation lines of a multi-line string don't end a body
+        let continues_literal = line
+            .pieces
+            .first()
+            .is_some_and(|(segment, _)| *segment == Segment::Literal)
+            && lines[..i]
+                .iter()
+                .rev()
+                .find(|line| line.kind() != LineKind::Blank)
+                .and_then(|line| line.pieces.last())
+                .is_some_and(|(segment, _)| *segment == Segment::Literal);
+
+        let logic = line.logic();
+        let trimmed = logic.trim_start();
+        let indent = logic.len() - trimmed.len();
+        if !continues_literal {
+            while stack.last().is_some_and(|open| open.level >= indent) {
+                let mut open = stack.pop().unwrap();
+                open.function.end_line = last_code_line;
+                functions.push(open.function);
+            }
+        }
+
+        let definition = trimmed
+            .strip_prefix("async ")
+            .unwrap_or(trimmed)
+            .strip_prefix("def ");
+        if let Some(rest) = definition {
+            stack.push(open(identifier(rest.trim_start()), number, indent));
+        } else if let Some(open) = stack.last_mut() {
+            open.function.complexity += count_decision_points(&logic, rules);
+        }
+        last_code_line = number;
+    }
+
+    for mut open in stack.into_iter().rev() {
+        open.function.end_line = last_code_line;
+        functions.push(open.function);
+    }
+    functions
+}
+
+/// Words that start statements or expressions that look like a call followed by
+/// a block, but aren't function headers.
+const CONTROL: &[&str] = &[
+    "if",
+    "else",
+    "for",
+    "while",
+    "switch",
+    "catch",
+    "return",
+    "do",
+    "case",
+    "new",
+    "throw",
+    "sizeof",
+    "try",
+    "typedef",
+    "using",
+    "synchronized",
+    "foreach",
+    "elseif",
+    "match",
+    "loop",
+    "with",
+    "await",
+    "yield",
+    "delete",
+    "typeof",
+    "function",
+    "lock",
+    "fixed",
+];
+
+/// The name of the function `logic` starts the header of, if it does.
+fn header(logic: &str, syntax: Syntax) -> Option<String> {
+    let trimmed = logic.trim().trim_start_matches('}').trim_start();
+    match syntax {
+        Syntax::Rust => after_word(trimmed, "fn")
+            .map(identifier)
+            .filter(|name| !name.is_empty()),
+        Syntax::Go => {
+            let Some(rest) = trimmed.strip_prefix("func ") else {
+                // A function literal, e.g. `go func() {`
+                return after_word(trimmed, "func")
+                    .filter(|rest| rest.starts_with('('))
+                    .map(|_| ANONYMOUS.to_string());
+            };
+            let rest = rest.trim_start();
+            // A method's receiver comes before its name
+            let rest = match rest.strip_prefix('(') {
+                Some(receiver) => receiver.split_once(')')?.1.trim_start(),
+                None => rest,
+            };
+            Some(identifier(rest)).filter(|name| !name.is_empty())
+        }
+        Syntax::Php => after_word(trimmed, "function").map(|rest| named(identifier(rest))),
+        Syntax::Keyword(keyword) => after_word(trimmed, keyword)
+            .map(keyword_function_name)
+            .filter(|name| !name.is_empty()),
+        Syntax::CLike => c_like_header(trimmed),
+        Syntax::JavaScript => javascript_header(trimmed),
+        Syntax::Python => None,
+    }
+}
+
+/// C, C++ and Java: a return type and modifiers, `name(`, then a parameter
+/// list. Control statements, assignments and calls on objects don't count.
+fn c_like_header(trimmed: &str) -> Option<String> {
+    if trimmed.starts_with('#') || CONTROL.contains(&identifier(trimmed).as_str()) {
+        return None;
+    }
+    let paren = trimmed.find('(')?;
+    let before = trimmed[..paren].trim_end();
+    let name_start = before
+        .rfind(|c: char| !(c.is_alphanumeric() || c == '_' || c == ':' || c == '~'))
+        .map_or(0, |i| i + 1);
+    let name = &before[name_start..];
+    // Types may hold `&`, `*`, anything between `<>` and C#'s nullable `T?`,
+    // but not what expressions are made of
+    let mut generics = 0;
+    let mut previous = ' ';
+    let prefix: String = before[..name_start]
+        .chars()
+        .filter(|&c| {
+            match c {
+                '<' => generics += 1,
+                '>' => generics -= 1,
+                _ => {}
+            }
+            let nullable = c == '?' && (previous.is_alphanumeric() || previous == '>');
+            previous = c;
+            generics == 0 && !nullable
+        })
+        .collect();
+    let valid = !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && !CONTROL.contains(&name)
+        && !prefix.contains(['=', '.', '(', '!', '|', '?', '+', '-', ','])
+        && parameter_list(&trimmed[paren..]);
+    valid.then(|| name.to_string())
+}
+
+/// Whether `text`, starting at `(`, reads as a parameter list and what may
+/// follow one before the body (`const`, `throws E`, `-> T`, an initializer list).
+/// A list still open at the end of the line continues on the next.
+fn parameter_list(text: &str) -> bool {
+    let mut depth = 0;
+    for (i, ch) in text.char_indices() {
+        match ch {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    let tail = text[i + 1..].split('{').next().unwrap_or_default().trim();
+                    return tail.starts_with(':') || !tail.contains(['(', ')', ';', '=']);
+                }
+            }
+            // A block or statement inside the parentheses makes it a call
+            '{' | ';' => return false,
+            _ => {}
+        }
+    }
+    true
+}
+
+/// The name in what follows `fun`, `func` or `def`: past type parameters and
+/// the receiver of an extension, e.g. `second` in `<T> List<T>.second()`.
+fn keyword_function_name(rest: &str) -> String {
+    let mut generics = 0;
+    let mut signature = String::new();
+    for c in rest.chars() {
+        match c {
+            '<' => generics += 1,
+            '>' => generics -= 1,
+            '(' | ':' | '=' | '{' if generics == 0 => break,
+            _ if generics == 0 => signature.push(c),
+            _ => {}
+        }
+    }
+    let name = signature.trim().rsplit('.').next().unwrap_or_default();
+    identifier(name.trim_start())
+}
+
+/// JavaScript and TypeScript: `function` with or without a name, arrow
+/// functions with a block body and class methods, all opening their body on
+/// the header line.
+fn javascript_header(trimmed: &str) -> Option<String> {
+    if !trimmed.ends_with('{') {
+        return None;
+    }
+    if let Some(rest) = after_word(trimmed, "function") {
+        return Some(named(identifier(rest.trim_start_matches('*').trim_start())));
+    }
+    if let Some(arrow) = trimmed.find("=>") {
+        // `const name = (…) => {` or `name: (…) => {`
+        let before = &trimmed[..arrow];
+        let name = before
+            .split_once(['=', ':'])
+            .map(|(target, _)| {
+                let target = target.trim();
+                target[target.rfind(' ').map_or(0, |i| i + 1)..].to_string()
+            })
+            .filter(|name| !name.contains(['(', ')', ',', '.']) && !name.is_empty());
+        return Some(named(name.unwrap_or_default()));
+    }
+
+    // A method: modifiers, then `name(…)`, then an optional return type
+    let mut rest = trimmed;
+    for modifier in [
+        "export ",
+        "public ",
+        "private ",
+        "protected ",
+        "static ",
+        "async ",
+        "override ",
+        "get ",
+        "set ",
+        "*",
+    ] {
+        rest = rest.strip_prefix(modifier).unwrap_or(rest).trim_start();
+    }
+    let name = identifier(rest);
+    let after = rest[name.len()..].trim_start();
+    (after.starts_with('(')
+        && parameter_list(after)
+        && !name.is_empty()
+        && !CONTROL.contains(&name.as_str()))
+    .then_some(name)
+}
+
+/// What follows `word` where it appears as a whole word.
+fn after_word<'a>(text: &'a str, word: &str) -> Option<&'a str> {
+    let mut offset = 0;
+    while let Some(found) = text[offset..].find(word) {
+        let start = offset + found;
+        let end = start + word.len();
+        let boundary_before = text[..start]
+            .chars()
+            .next_back()
+            .is_none_or(|c| !(c.is_alphanumeric() || c == '_'));
+        let boundary_after = text[end..]
+            .chars()
+            .next()
+            .is_none_or(|c| !(c.is_alphanumeric() || c == '_'));
+        if boundary_before && boundary_after {
+            return Some(text[end..].trim_start());
+        }
+        offset = end;
+    }
+    None
+}
+
+/// The identifier `text` starts with, possibly empty.
+fn identifier(text: &str) -> String {
+    text.chars()
+        .take_while(|c| c.is_alphanumeric() || *c == '_' || *c == '$')
+        .collect()
+}
+
+fn named(name: String) -> String {
+    if name.is_empty() {
+        ANONYMOUS.to_string()
+    } else {
+        name
+    }
+}
+
+/// The highest, mean and 90th percentile (nearest rank) complexity of
+/// `functions`; zero without any.
+pub fn complexity_stats(functions: &[Function]) -> (usize, usize, usize) {
+    if functions.is_empty() {
+        return (0, 0, 0);
+    }
+    let mut values: Vec<usize> = functions.iter().map(|f| f.complexity).collect();
+    values.sort_unstable();
+    let max = values[values.len() - 1];
+    let mean = values.iter().sum::<usize>() / values.len();
+    let p90 = values[(values.len() * 9).div_ceil(10) - 1];
+    (max, mean, p90)
+}
@@ -0,0 +1,207 @@
+use crate::analyzer::{Row, aggregate_values};
+use crate::metrics::{Metric, find_metric, metric_names};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Exit status used by the CLI when any gate is violated.
+pub const GATE_FAILURE_EXIT_CODE: i32 = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GateScope {
+    /// Checked against every analyzed file.
+    File,
+    /// Checked against the aggregated value over all analyzed files.
+    Total,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Comparison {
+    Greater,
+    GreaterOrEqual,
+    Less,
+    LessOrEqual,
+}
+
+impl Comparison {
+    fn fails(self, value: usize, limit: usize) -> bool {
+        match self {
+            Comparison::Greater => value > limit,
+            Comparison::GreaterOrEqual => value >= limit,
+            Comparison::Less => value < limit,
+            Comparison::LessOrEqual => value <= limit,
+        }
+    }
+}
+
+/// A failure condition such as `complexity>40`, `duplicates>30%` or
+/// `total.lines>200000`, optionally restricted to paths matching some globs.
+#[derive(Clone)]
+pub struct Gate {
+    pub rule: String,
+    pub scope: GateScope,
+    pub metric: &'static dyn Metric,
+    pub comparison: Comparison,
+    pub limit: usize,
+    pub paths: Vec<glob::Pattern>,
+}
+
+#[derive(Debug)]
+pub struct Violation {
+    pub rule: String,
+    /// The offending file, or `None` for total gates.
+    pub path: Option<PathBuf>,
+    pub metric: &'static str,
+    pub value: usize,
+    pub limit: usize,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.path {
+            Some(path) => write!(
+                f,
+                "{}: {} {} = {}",
+                self.rule,
+                path.display(),
+                self.metric,
+                self.value
+            ),
+            None => write!(f, "{}: total {} = {}", self.rule, self.metric, self.value),
+        }
+    }
+}
+
+impl FromStr for Gate {
+    type Err = String;
+
+    fn from_str(rule: &str) -> Result<Self, Self::Err> {
+        let trimmed: String = rule.chars().filter(|c| !c.is_whitespace()).collect();
+
+        let (name, comparison, limit) = [
+            (">=", Comparison::GreaterOrEqual),
+            ("<=", Comparison::LessOrEqual),
+            (">", Comparison::Greater),
+            ("<", Comparison::Less),
+        ]
+        .iter()
+        .find_map(|(op, comparison)| {
+            trimmed
+                .split_once(op)
+                .map(|(name, limit)| (name, *comparison, limit))
+        })
+        .ok_or_else(|| {
+            format!("invalid gate '{rule}': expected <metric><op><limit>, e.g. complexity>40")
+        })?;
+
+        let (scope, name) = match name.strip_prefix("total.") {
+            Some(name) => (GateScope::Total, name),
+            None => (GateScope::File, name),
+        };
+
+        let metric = find_metric(name).ok_or_else(|| {
+            format!(
+                "invalid gate '{rule}': unknown metric '{name}' (expected one of {})",
+                metric_names().join(", ")
+            )
+        })?;
+
+        let (limit, percent) = match limit.strip_suffix('%') {
+            Some(limit) => (limit, true),
+            None => (limit, false),
+        };
+        if percent && metric.unit() != "percent" {
+            return Err(format!(
+                "invalid gate '{rule}': {name} is measured in {}, not percent",
+                metric.unit()
+            ));
+        }
+        let limit = limit
+            .parse()
+            .map_err(|_| format!("invalid gate '{rule}': '{limit}' is not a whole number"))?;
+
+        Ok(Gate {
+            rule: rule.trim().to_string(),
+            scope,
+            metric,
+            comparison,
+            limit,
+            paths: Vec::new(),
+        })
+    }
+}
+
+impl Gate {
+    /// Restricts a file gate to paths matching any of `patterns`.
+    pub fn with_paths(mut self, patterns: Vec<glob::Pattern>) -> Self {
+        self.paths = patterns;
+        self
+    }
+
+    /// Paths are matched both as walked and relative to the analyzed root.
+    fn applies_to(&self, path: &Path, root: &Path) -> bool {
+        if self.paths.is_empty() {
+            return true;
+        }
+
+        let relative = path.strip_prefix(root).unwrap_or(path);
+        self.paths
+            .iter()
+            .any(|pattern| pattern.matches_path(path) || pattern.matches_path(relative))
+    }
+}
+
+/// Checks every gate against the per-file rows and their totals. `rows` should be
+/// the unranked, unlimited rows from `Analyzer::analyze` with values in `metrics`
+/// order. When several file gates for the same metric match a path, the last one
+/// wins, so later (more specific) rules can loosen or tighten earlier ones.
+pub fn evaluate_gates(
+    gates: &[Gate],
+    metrics: &[&'static dyn Metric],
+    rows: &[Row],
+    root: &Path,
+) -> Vec<Violation> {
+    let column = |metric: &dyn Metric| metrics.iter().position(|m| m.name() == metric.name());
+    let mut violations = Vec::new();
+
+    for row in rows {
+        for (i, metric) in metrics.iter().enumerate() {
+            let gate = gates.iter().rev().find(|gate| {
+                gate.scope == GateScope::File
+                    && gate.metric.name() == metric.name()
+                    && gate.applies_to(&row.path, root)
+            });
+            if let Some(gate) = gate
+                && gate.comparison.fails(row.values[i], gate.limit)
+            {
+                violations.push(Violation {
+                    rule: gate.rule.clone(),
+                    path: Some(row.path.clone()),
+                    metric: metric.name(),
+                    value: row.values[i],
+                    limit: gate.limit,
+                });
+            }
+        }
+    }
+
+    violations.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let totals = aggregate_values(metrics, rows.iter().map(|row| &row.values));
+    for gate in gates.iter().filter(|gate| gate.scope == GateScope::Total) {
+        let Some(i) = column(gate.metric) else {
+            continue;
+        };
+        if gate.comparison.fails(totals[i], gate.limit) {
+            violations.push(Violation {
+                rule: gate.rule.clone(),
+                path: None,
+                metric: gate.metric.name(),
+                value: totals[i],
+                limit: gate.limit,
+            });
+        }
+    }
+
+    violations
+}
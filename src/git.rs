@@ -1,96 +1,563 @@
-use std::collections::HashMap;
-use std::path::Path;
-use std::process::Command;
+use crate::trend::parse_interval;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// One commit as recorded in the history index.
+#[derive(Clone, Debug)]
+pub struct Commit {
+    pub hash: String,
+    /// The author's identity after `.mailmap` and [`AuthorRules`] are applied.
+    pub author: String,
+    pub email: String,
+    /// Committer timestamp, seconds since the epoch.
+    pub timestamp: u64,
+    pub files: Vec<FileChange>,
+}
 
-pub fn calculate_churn(path: &Path, days: u32) -> Result<usize, std::io::Error> {
-    let path_str = path.to_string_lossy();
+/// A file touched by a commit, with `--numstat` line counts (zero for binary files).
+#[derive(Clone, Debug)]
+pub struct FileChange {
+    /// Path relative to the repository root after the commit.
+    pub path: String,
+    /// The path before the commit, if the commit renamed the file.
+    pub previous_path: Option<String>,
+    pub added: usize,
+    pub deleted: usize,
+}
 
-    let output = Command::new("git")
-        .args([
-            "log",
-            "--oneline",
-            &format!("--since={days}days"),
-            "--",
-            &path_str,
-        ])
-        .output();
-
-    match output {
-        Ok(output) => {
-            if output.status.success() {
-                let lines = String::from_utf8_lossy(&output.stdout);
-                Ok(lines.lines().count())
-            } else {
-                Ok(0)
-            }
+/// What churn measures for a file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ChurnKind {
+    /// Commits touching the file.
+    #[default]
+    Commits,
+    /// Lines added.
+    Added,
+    /// Lines deleted.
+    Deleted,
+    /// Lines added plus lines deleted.
+    Changed,
+    /// Lines changed per current line of the file, in percent.
+    Relative,
+}
+
+impl ChurnKind {
+    /// Name of the metric measuring churn this way.
+    pub fn metric_name(self) -> &'static str {
+        match self {
+            ChurnKind::Commits => "churn",
+            ChurnKind::Added => "churn-added",
+            ChurnKind::Deleted => "churn-deleted",
+            ChurnKind::Changed => "churn-lines",
+            ChurnKind::Relative => "churn-relative",
         }
-        Err(_) => Ok(0),
     }
 }
 
-pub fn get_primary_author(path: &Path) -> Option<String> {
-    let path_str = path.to_string_lossy();
+/// Which commits the history covers. The default is all of `HEAD`'s history.
+#[derive(Clone, Debug, Default)]
+pub struct HistoryRange {
+    /// Only commits after this date: anything `git log --since` accepts, or a
+    /// span such as `6m` before now.
+    pub since: Option<String>,
+    /// Only commits before this date.
+    pub until: Option<String>,
+    /// A branch, commit or range such as `main..feature` to walk instead of `HEAD`.
+    pub revisions: Option<String>,
+}
 
-    let output = Command::new("git")
-        .args(["log", "--format=%an", "--", &path_str])
-        .output();
-
-    match output {
-        Ok(output) => {
-            if output.status.success() {
-                let authors_text = String::from_utf8_lossy(&output.stdout);
-                let mut author_counts: HashMap<String, usize> = HashMap::new();
-
-                for author in authors_text.lines() {
-                    let author = author.trim().to_string();
-                    if !author.is_empty() {
-                        *author_counts.entry(author).or_insert(0) += 1;
-                    }
+impl HistoryRange {
+    pub fn is_empty(&self) -> bool {
+        self.since.is_none() && self.until.is_none() && self.revisions.is_none()
+    }
+}
+
+/// How commit authors are identified. `.mailmap` is always honored first.
+#[derive(Clone, Debug, Default)]
+pub struct AuthorRules {
+    /// Name or email (matched case-insensitively) → canonical identity.
+    pub aliases: HashMap<String, String>,
+    /// Identify authors by email instead of name.
+    pub by_email: bool,
+    /// Commits by authors whose name or email matches are left out entirely.
+    pub exclude: Vec<glob::Pattern>,
+}
+
+impl AuthorRules {
+    pub fn alias(&mut self, alias: &str, identity: impl Into<String>) {
+        self.aliases.insert(alias.to_lowercase(), identity.into());
+    }
+
+    /// The identity to record for a commit, or `None` if the author is excluded.
+    pub fn resolve(&self, name: &str, email: &str) -> Option<String> {
+        let options = glob::MatchOptions {
+            case_sensitive: false,
+            ..Default::default()
+        };
+        if self.exclude.iter().any(|pattern| {
+            pattern.matches_with(name, options) || pattern.matches_with(email, options)
+        }) {
+            return None;
+        }
+
+        let alias = self
+            .aliases
+            .get(&name.to_lowercase())
+            .or_else(|| self.aliases.get(&email.to_lowercase()));
+        Some(match alias {
+            Some(identity) => identity.clone(),
+            None if self.by_email && !email.is_empty() => email.to_lowercase(),
+            None => name.to_string(),
+        })
+    }
+}
+
+/// The history of a repository, read with a single `git log` so every git metric
+/// for every file can be answered from memory instead of spawning git per file.
+pub struct GitHistory {
+    root: PathBuf,
+    range: HistoryRange,
+    authors: AuthorRules,
+    /// Revision to blame at when the range ends before `HEAD`.
+    blame_revision: Option<String>,
+    /// Newest first.
+    commits: Vec<Commit>,
+    /// Latest repository-relative path → indices into `commits`, newest first.
+    /// Renames are followed, so a file's entry covers its whole lineage.
+    by_path: HashMap<String, Vec<usize>>,
+}
+
+impl GitHistory {
+    /// Reads the commits in `range`. Fails if `path` isn't inside a git work tree
+    /// or bare repository, or git rejects the range. The log isn't limited to `path`, since files moved
+    /// in from elsewhere keep their history and metrics look at what else changed
+    /// alongside a file.
+    pub fn load(path: &Path, range: &HistoryRange, authors: &AuthorRules) -> Result<Self, String> {
+        let dir = containing_dir(path)?;
+        // A bare repository has no work tree; its files are keyed from the
+        // repository directory, as `Analyzer::tree` lays them out
+        let toplevel = if is_bare_repository(&dir) {
+            git_output(&dir, &["rev-parse", "--absolute-git-dir"])?
+        } else {
+            git_output(&dir, &["rev-parse", "--show-toplevel"])?
+        };
+        let root = PathBuf::from(toplevel.trim())
+            .canonicalize()
+            .map_err(|e| e.to_string())?;
+
+        let mut args = vec![
+            "log".to_string(),
+            "-z".to_string(),
+            "--find-renames".to_string(),
+            "--numstat".to_string(),
+            "--format=%x1e%H%x1f%aN%x1f%aE%x1f%ct".to_string(),
+        ];
+        if let Some(since) = &range.since {
+            args.push(format!("--since={}", git_date(since)));
+        }
+        if let Some(until) = &range.until {
+            args.push(format!("--until={}", git_date(until)));
+        }
+        if let Some(revisions) = &range.revisions {
+            args.push(revisions.clone());
+        }
+        args.push("--".to_string());
+
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        let log = git_output(&root, &args)?;
+
+        // Blame takes a revision or range and --since, but no end date
+        let blame_revision = match (&range.revisions, &range.until) {
+            (Some(revisions), _) => Some(revisions.clone()),
+            (None, Some(until)) => Some(
+                git_output(
+                    &root,
+                    &[
+                        "rev-list",
+                        "-1",
+                        &format!("--until={}", git_date(until)),
+                        "HEAD",
+                    ],
+                )?
+                .trim()
+                .to_string(),
+            ),
+            (None, None) => None,
+        };
+
+        let mut history = GitHistory {
+            root,
+            range: range.clone(),
+            authors: authors.clone(),
+            blame_revision,
+            commits: Vec::new(),
+            by_path: HashMap::new(),
+        };
+        // Older name → latest name, built up while walking back in time
+        let mut lineage = HashMap::new();
+        for record in log.split('\x1e').filter(|record| !record.trim().is_empty()) {
+            let Some(mut commit) = parse_commit(record) else {
+                continue;
+            };
+            match authors.resolve(&commit.author, &commit.email) {
+                Some(author) => {
+                    commit.author = author;
+                    history.push(commit, &mut lineage);
+                }
+                // Excluded commits still carry renames the lineage has to follow
+                None => {
+                    follow_renames(&commit, &mut lineage);
+                }
+            }
+        }
+        Ok(history)
+    }
+
+    /// Adds the next older commit, filing each change under the file's latest name.
+    fn push(&mut self, commit: Commit, lineage: &mut HashMap<String, String>) {
+        let index = self.commits.len();
+        for latest in follow_renames(&commit, lineage) {
+            self.by_path.entry(latest).or_default().push(index);
+        }
+        self.commits.push(commit);
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The range this history was loaded with.
+    pub fn range(&self) -> &HistoryRange {
+        &self.range
+    }
+
+    pub fn commits(&self) -> &[Commit] {
+        &self.commits
+    }
+
+    /// Commits that touched `path`, newest first.
+    pub fn file_commits(&self, path: &Path) -> Vec<&Commit> {
+        self.key(path)
+            .and_then(|key| self.by_path.get(&key))
+            .map(|indices| indices.iter().map(|&i| &self.commits[i]).collect())
+            .unwrap_or_default()
+    }
+
+    /// Surviving lines of `path` per author, from `git blame`, counting only lines
+    /// last changed within the range and by authors that aren't excluded.
+    /// Uncommitted lines aren't counted.
+    pub fn blame_lines(&self, path: &Path) -> BTreeMap<String, usize> {
+        let mut lines = BTreeMap::new();
+        let Some(key) = self.key(path) else {
+            return lines;
+        };
+
+        let mut args = vec![
+            "blame".to_string(),
+            "--line-porcelain".to_string(),
+            "--root".to_string(),
+        ];
+        if let Some(since) = &self.range.since {
+            args.push(format!("--since={}", git_date(since)));
+        }
+        if let Some(revision) = &self.blame_revision {
+            args.push(revision.clone());
+        }
+        args.push("--".to_string());
+        args.push(key);
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        let Ok(blame) = git_output(&self.root, &args) else {
+            return lines;
+        };
+
+        let (mut name, mut email) = ("", "");
+        let mut counted = true;
+        for line in blame.lines() {
+            if line.starts_with('\t') {
+                // The line's content ends its entry
+                if counted && let Some(author) = self.authors.resolve(name, email) {
+                    *lines.entry(author).or_insert(0) += 1;
                 }
+            } else if let Some(value) = line.strip_prefix("author ") {
+                name = value;
+            } else if let Some(value) = line.strip_prefix("author-mail ") {
+                email = value.trim_start_matches('<').trim_end_matches('>');
+            } else if line == "boundary" {
+                counted = false;
+            } else if line.len() > 40
+                && line.as_bytes()[40] == b' '
+                && line.as_bytes()[..40].iter().all(u8::is_ascii_hexdigit)
+            {
+                counted = !line.starts_with("0000000000000000000000000000000000000000");
+            }
+        }
+        lines
+    }
 
-                author_counts
-                    .into_iter()
-                    .max_by_key(|(_, count)| *count)
-                    .map(|(author, _)| author)
-            } else {
-                None
+    /// Names `path` had earlier in its history, most recent first.
+    pub fn former_paths(&self, path: &Path) -> Vec<String> {
+        let Some(key) = self.key(path) else {
+            return Vec::new();
+        };
+        let mut names: Vec<String> = Vec::new();
+        for commit in self.file_commits(path) {
+            for file in &commit.files {
+                if let Some(previous) = &file.previous_path
+                    && (file.path == key || names.contains(&file.path))
+                    && !names.contains(previous)
+                {
+                    names.push(previous.clone());
+                }
             }
         }
-        Err(_) => None,
+        names
+    }
+
+    /// `path` relative to the repository root, as git prints it. Files that aren't
+    /// on disk (deleted, or only in a commit's tree) are resolved through their
+    /// nearest directory that is.
+    pub fn key(&self, path: &Path) -> Option<String> {
+        let mut existing = path;
+        let mut missing = Vec::new();
+        let absolute = loop {
+            if let Ok(absolute) = existing.canonicalize() {
+                break missing.iter().rev().fold(absolute, |dir, name| dir.join(name));
+            }
+            missing.push(existing.file_name()?);
+            existing = match existing.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent,
+                _ => Path::new("."),
+            };
+        };
+        let relative = absolute.strip_prefix(&self.root).ok()?;
+        Some(
+            relative
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/"),
+        )
     }
 }
 
-pub fn calculate_file_age_days(path: &Path) -> Result<usize, std::io::Error> {
-    let path_str = path.to_string_lossy();
+/// Maps each file `commit` touched to its latest name, then records the names
+/// it renamed from so older commits map to the same file.
+fn follow_renames(commit: &Commit, lineage: &mut HashMap<String, String>) -> Vec<String> {
+    commit
+        .files
+        .iter()
+        .map(|file| {
+            // Older commits using this name are about whatever had it before
+            let latest = lineage
+                .remove(&file.path)
+                .unwrap_or_else(|| file.path.clone());
+            if let Some(previous) = &file.previous_path {
+                lineage.insert(previous.clone(), latest.clone());
+            }
+            latest
+        })
+        .collect()
+}
+
+/// `path` itself if it's a directory, else the directory it's in, made absolute.
+fn containing_dir(path: &Path) -> Result<PathBuf, String> {
+    let absolute = path
+        .canonicalize()
+        .map_err(|e| format!("{}: {e}", path.display()))?;
+    if absolute.is_dir() {
+        Ok(absolute)
+    } else {
+        Ok(absolute.parent().unwrap_or(&absolute).to_path_buf())
+    }
+}
 
+fn git_output(dir: &Path, args: &[&str]) -> Result<String, String> {
     let output = Command::new("git")
-        .args(["log", "-1", "--format=%ct", "--", &path_str])
-        .output();
-
-    match output {
-        Ok(output) => {
-            if output.status.success() {
-                let timestamp_str = String::from_utf8_lossy(&output.stdout);
-                if let Ok(timestamp) = timestamp_str.trim().parse::<u64>() {
-                    let now = std::time::SystemTime::now()
-                        .duration_since(std::time::UNIX_EPOCH)
-                        .unwrap_or_default()
-                        .as_secs();
-
-                    let age_seconds = now.saturating_sub(timestamp);
-                    let age_days = age_seconds / 86400; // seconds per day
-
-                    Ok(age_days as usize)
-                } else {
-                    // No valid timestamp, fallback to file modification time
-                    file_system_age_days(path)
-                }
-            } else {
-                // Git command failed, fallback to file modification time
-                file_system_age_days(path)
-            }
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .map_err(|e| format!("cannot run git: {e}"))?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+/// Parses one `-z` log record: the header, then `added\tdeleted\tpath` entries,
+/// with renames written as `added\tdeleted\t` followed by the old and new paths
+/// as separate NUL-terminated fields.
+fn parse_commit(record: &str) -> Option<Commit> {
+    let mut fields = record.split('\0');
+    let mut header = fields.next()?.split('\x1f');
+    let hash = header.next()?.to_string();
+    let author = header.next()?.trim().to_string();
+    let email = header.next()?.trim().to_string();
+    let timestamp = header.next()?.trim().parse().ok()?;
+
+    let mut files = Vec::new();
+    while let Some(field) = fields.next() {
+        let field = field.trim_start_matches('\n');
+        let mut stat = field.splitn(3, '\t');
+        let (Some(added), Some(deleted), Some(path)) = (stat.next(), stat.next(), stat.next())
+        else {
+            continue;
+        };
+        let (path, previous_path) = if path.is_empty() {
+            let (Some(old), Some(new)) = (fields.next(), fields.next()) else {
+                break;
+            };
+            (new.to_string(), Some(old.to_string()))
+        } else {
+            (path.to_string(), None)
+        };
+        files.push(FileChange {
+            path,
+            previous_path,
+            added: added.parse().unwrap_or(0),
+            deleted: deleted.parse().unwrap_or(0),
+        });
+    }
+
+    Some(Commit {
+        hash,
+        author,
+        email,
+        timestamp,
+        files,
+    })
+}
+
+/// `date` as git's `--since`/`--until` should get it, with a span such as `1y`
+/// resolved first; git would read it as a day of the current month.
+fn git_date(date: &str) -> String {
+    match parse_interval(date) {
+        Ok(span) => format!("@{}", now_secs().saturating_sub(span)),
+        Err(_) => date.to_string(),
+    }
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Start of the `--days` window in seconds since the epoch, or 0 when the history
+/// was loaded with an explicit range, which replaces the window.
+pub(crate) fn window_start(history: &GitHistory, days: u32) -> u64 {
+    if history.range().is_empty() {
+        now_secs().saturating_sub(u64::from(days) * 86400)
+    } else {
+        0
+    }
+}
+
+/// Commits touching `path` in the last `days` days, or in the whole history when
+/// it was loaded with an explicit range.
+pub fn calculate_churn(history: &GitHistory, path: &Path, days: u32) -> usize {
+    let since = window_start(history, days);
+    history
+        .file_commits(path)
+        .iter()
+        .filter(|commit| commit.timestamp >= since)
+        .count()
+}
+
+/// Lines added and deleted in `path` by the commits [`calculate_churn`] counts,
+/// following renames. Binary changes count as zero lines.
+pub fn calculate_line_churn(history: &GitHistory, path: &Path, days: u32) -> (usize, usize) {
+    let since = window_start(history, days);
+    let Some(mut name) = history.key(path) else {
+        return (0, 0);
+    };
+
+    let (mut added, mut deleted) = (0, 0);
+    // Newest first, so the name only changes back to what the file was called
+    for commit in history.file_commits(path) {
+        let Some(file) = commit.files.iter().find(|file| file.path == name) else {
+            continue;
+        };
+        if commit.timestamp >= since {
+            added += file.added;
+            deleted += file.deleted;
+        }
+        if let Some(previous) = &file.previous_path {
+            name = previous.clone();
+        }
+    }
+    (added, deleted)
+}
+
+/// Commit counts per author name for `path`.
+pub fn author_counts(history: &GitHistory, path: &Path) -> HashMap<String, usize> {
+    let mut author_counts: HashMap<String, usize> = HashMap::new();
+    for commit in history.file_commits(path) {
+        if !commit.author.is_empty() {
+            *author_counts.entry(commit.author.clone()).or_insert(0) += 1;
         }
-        Err(_) => file_system_age_days(path),
+    }
+    author_counts
+}
+
+pub fn get_primary_author(history: &GitHistory, path: &Path) -> Option<String> {
+    author_counts(history, path)
+        .into_iter()
+        // Ties go to the alphabetically first name so output is stable
+        .max_by(|(a, a_count), (b, b_count)| a_count.cmp(b_count).then(b.cmp(a)))
+        .map(|(author, _)| author)
+}
+
+/// The fewest authors who together account for more than half of `counts`
+/// (commits or lines per author), largest share first. Its length is the bus
+/// factor: how many people would have to leave before most of the knowledge goes.
+pub fn bus_factor_authors(counts: &BTreeMap<String, usize>) -> Vec<(&str, usize)> {
+    let total: usize = counts.values().sum();
+    let mut authors: Vec<(&str, usize)> = counts
+        .iter()
+        .filter(|(_, count)| **count > 0)
+        .map(|(author, count)| (author.as_str(), *count))
+        .collect();
+    // Ties go to the alphabetically first name so output is stable
+    authors.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+
+    let mut covered = 0;
+    let needed = authors
+        .iter()
+        .take_while(|(_, count)| {
+            let more_needed = covered * 2 <= total;
+            covered += count;
+            more_needed
+        })
+        .count();
+    authors.truncate(needed);
+    authors
+}
+
+/// Days since the last commit touching `path`, or since it was modified on disk
+/// when git has no history for it.
+pub fn calculate_file_age_days(
+    history: Option<&GitHistory>,
+    path: &Path,
+) -> Result<usize, std::io::Error> {
+    let last_commit = history.and_then(|history| {
+        history
+            .file_commits(path)
+            .iter()
+            .map(|commit| commit.timestamp)
+            .max()
+    });
+
+    match last_commit {
+        Some(timestamp) => {
+            let age_seconds = now_secs().saturating_sub(timestamp);
+            Ok((age_seconds / 86400) as usize) // seconds per day
+        }
+        None => file_system_age_days(path),
     }
 }
 
@@ -108,154 +575,199 @@ fn file_system_age_days(path: &Path) -> Result<usize, std::io::Error> {
     }
 }
 
-pub fn calculate_ownership_percentage(path: &Path) -> Result<usize, std::io::Error> {
-    let path_str = path.to_string_lossy();
-
-    let output = Command::new("git")
-        .args(["log", "--format=%an", "--", &path_str])
-        .output();
-
-    match output {
-        Ok(output) => {
-            if output.status.success() && !output.stdout.is_empty() {
-                let authors_text = String::from_utf8_lossy(&output.stdout);
-                let mut author_counts: HashMap<String, usize> = HashMap::new();
-                let mut total_commits = 0;
-
-                for author in authors_text.lines() {
-                    let author = author.trim().to_string();
-                    if !author.is_empty() {
-                        *author_counts.entry(author).or_insert(0) += 1;
-                        total_commits += 1;
-                    }
-                }
-
-                if total_commits == 0 {
-                    return Ok(0);
-                }
+/// Share of the commits touching `path` made by its most frequent author.
+pub fn calculate_ownership_percentage(history: &GitHistory, path: &Path) -> usize {
+    let author_counts = author_counts(history, path);
+    let total_commits: usize = author_counts.values().sum();
+    if total_commits == 0 {
+        return 0;
+    }
 
-                // Find the author with the most commits
-                let max_commits = author_counts.values().max().unwrap_or(&0);
-                let ownership_percentage = (*max_commits * 100) / total_commits;
+    let max_commits = author_counts.values().max().unwrap_or(&0);
+    (*max_commits * 100) / total_commits
+}
 
-                Ok(ownership_percentage)
-            } else {
-                // No git history, return 0 to indicate no ownership data
-                Ok(0)
-            }
-        }
-        Err(_) => Ok(0),
+/// Share of the commits touching `path` that touched no other file.
+pub fn calculate_isolation_percentage(history: &GitHistory, path: &Path) -> usize {
+    let commits = history.file_commits(path);
+    if commits.is_empty() {
+        return 0;
     }
+
+    let single_file_commits = commits
+        .iter()
+        .filter(|commit| commit.files.len() == 1)
+        .count();
+    (single_file_commits * 100) / commits.len()
 }
 
-pub fn calculate_isolation_percentage(path: &Path) -> Result<usize, std::io::Error> {
-    let path_str = path.to_string_lossy();
+/// Standard deviation, in days, of the intervals between commits touching `path`.
+pub fn calculate_rhythm_score(history: &GitHistory, path: &Path) -> usize {
+    let mut timestamps: Vec<u64> = history
+        .file_commits(path)
+        .iter()
+        .map(|commit| commit.timestamp)
+        .collect();
 
-    let output = Command::new("git")
-        .args(["log", "--format=%H", "--", &path_str])
-        .output();
-
-    match output {
-        Ok(output) => {
-            if output.status.success() && !output.stdout.is_empty() {
-                let commits_text = String::from_utf8_lossy(&output.stdout);
-                let commit_hashes: Vec<&str> = commits_text
-                    .lines()
-                    .filter(|line| !line.trim().is_empty())
-                    .collect();
-
-                if commit_hashes.is_empty() {
-                    return Ok(0);
-                }
+    if timestamps.len() < 2 {
+        return 0;
+    }
 
-                let mut single_file_commits = 0;
-
-                for commit_hash in &commit_hashes {
-                    let diff_output = Command::new("git")
-                        .args([
-                            "diff",
-                            "--name-only",
-                            &format!("{commit_hash}^"),
-                            commit_hash,
-                        ])
-                        .output();
-
-                    if let Ok(diff_result) = diff_output {
-                        if diff_result.status.success() {
-                            let changed_files = String::from_utf8_lossy(&diff_result.stdout);
-                            let file_count = changed_files
-                                .lines()
-                                .filter(|line| !line.trim().is_empty())
-                                .count();
-
-                            if file_count == 1 {
-                                single_file_commits += 1;
-                            }
-                        }
-                    }
-                }
+    timestamps.sort_unstable();
+    timestamps.reverse(); // newest first
 
-                let isolation_percentage = (single_file_commits * 100) / commit_hashes.len();
-                Ok(isolation_percentage)
-            } else {
-                Ok(0)
-            }
-        }
-        Err(_) => Ok(0),
+    let mut intervals: Vec<u64> = Vec::new();
+    for window in timestamps.windows(2) {
+        let interval_seconds = window[0] - window[1];
+        let interval_days = interval_seconds / 86400; // seconds per day
+        intervals.push(interval_days);
     }
+
+    // Calculate standard deviation of intervals
+    let mean = intervals.iter().sum::<u64>() as f64 / intervals.len() as f64;
+    let variance = intervals
+        .iter()
+        .map(|&x| {
+            let diff = x as f64 - mean;
+            diff * diff
+        })
+        .sum::<f64>()
+        / intervals.len() as f64;
+
+    let std_dev = variance.sqrt();
+    std_dev as usize
 }
 
-pub fn calculate_rhythm_score(path: &Path) -> Result<usize, std::io::Error> {
-    let path_str = path.to_string_lossy();
+/// Whether `path` is the directory of a bare repository, which has no work tree
+/// to walk.
+pub fn is_bare_repository(path: &Path) -> bool {
+    path.join("HEAD").is_file() && path.join("objects").is_dir() && path.join("refs").is_dir()
+}
 
-    let output = Command::new("git")
-        .args(["log", "--format=%ct", "--", &path_str])
-        .output();
-
-    match output {
-        Ok(output) => {
-            if output.status.success() && !output.stdout.is_empty() {
-                let timestamps_text = String::from_utf8_lossy(&output.stdout);
-                let mut timestamps: Vec<u64> = timestamps_text
-                    .lines()
-                    .filter_map(|line| line.trim().parse().ok())
-                    .collect();
-
-                if timestamps.len() < 2 {
-                    return Ok(0);
-                }
+/// A file in a commit's tree.
+#[derive(Clone, Debug)]
+pub struct TreeEntry {
+    /// Path relative to the directory the tree was listed for.
+    pub path: String,
+    pub blob: String,
+}
 
-                timestamps.sort_unstable();
-                timestamps.reverse(); // newest first
+/// Resolves a date to seconds since the epoch. A span such as `1y` or `2w` (see
+/// [`parse_interval`]) counts back from `now`; anything else is read the way
+/// `git log --since` reads it (`2 weeks ago`, `2024-01-01`). Git's parser can't
+/// be used for spans: it takes `1y` for the first of the current month.
+pub fn resolve_date(path: &Path, date: &str, now: u64) -> Result<u64, String> {
+    if let Ok(span) = parse_interval(date) {
+        return Ok(now.saturating_sub(span));
+    }
+    let dir = containing_dir(path)?;
+    let parsed = git_output(&dir, &["rev-parse", &format!("--since={date}")])?;
+    parsed
+        .trim()
+        .strip_prefix("--max-age=")
+        .and_then(|timestamp| timestamp.parse().ok())
+        .ok_or_else(|| format!("invalid date '{date}'"))
+}
 
-                let mut intervals: Vec<u64> = Vec::new();
-                for window in timestamps.windows(2) {
-                    let interval_seconds = window[0] - window[1];
-                    let interval_days = interval_seconds / 86400; // seconds per day
-                    intervals.push(interval_days);
-                }
+/// The newest commit on `revision`'s first-parent line committed at or before
+/// `timestamp`, with its commit time, or `None` if the history starts later.
+pub fn commit_at(
+    path: &Path,
+    revision: &str,
+    timestamp: u64,
+) -> Result<Option<(String, u64)>, String> {
+    let dir = containing_dir(path)?;
+    let output = git_output(
+        &dir,
+        &[
+            "log",
+            "-1",
+            "--first-parent",
+            &format!("--before=@{timestamp}"),
+            "--format=%H %ct",
+            revision,
+            "--",
+        ],
+    )?;
+    Ok(output.trim().split_once(' ').and_then(|(hash, time)| {
+        time.parse()
+            .ok()
+            .map(|timestamp| (hash.to_string(), timestamp))
+    }))
+}
 
-                if intervals.is_empty() {
-                    return Ok(0);
-                }
+/// The files under `path` in `commit`'s tree, without checking anything out.
+/// Submodules and symlinks are left out.
+pub fn tree_files(path: &Path, commit: &str) -> Result<Vec<TreeEntry>, String> {
+    let dir = containing_dir(path)?;
+    let pathspec = match path.file_name() {
+        Some(name) if path.is_file() => name.to_string_lossy().into_owned(),
+        _ => ".".to_string(),
+    };
+    let listing = git_output(&dir, &["ls-tree", "-r", "-z", commit, "--", &pathspec])?;
+    Ok(listing
+        .split('\0')
+        .filter_map(|entry| {
+            let (header, path) = entry.split_once('\t')?;
+            let mut fields = header.split(' ');
+            let (mode, kind, blob) = (fields.next()?, fields.next()?, fields.next()?);
+            (kind == "blob" && mode != "120000").then(|| TreeEntry {
+                path: path.to_string(),
+                blob: blob.to_string(),
+            })
+        })
+        .collect())
+}
 
-                // Calculate standard deviation of intervals
-                let mean = intervals.iter().sum::<u64>() as f64 / intervals.len() as f64;
-                let variance = intervals
-                    .iter()
-                    .map(|&x| {
-                        let diff = x as f64 - mean;
-                        diff * diff
-                    })
-                    .sum::<f64>()
-                    / intervals.len() as f64;
-
-                let std_dev = variance.sqrt();
-                Ok(std_dev as usize)
-            } else {
-                Ok(0)
+/// Contents of `blobs`, in order, read with a single `git cat-file --batch`.
+/// Missing objects read as empty.
+pub fn read_blobs(path: &Path, blobs: &[&str]) -> Result<Vec<Vec<u8>>, String> {
+    let dir = containing_dir(path)?;
+    let mut child = Command::new("git")
+        .arg("-C")
+        .arg(&dir)
+        .args(["cat-file", "--batch"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("cannot run git: {e}"))?;
+
+    // Write from another thread so a full stdout pipe can't stall both sides
+    let mut stdin = child.stdin.take().ok_or("cannot write to git")?;
+    let request: String = blobs.iter().map(|blob| format!("{blob}\n")).collect();
+    let writer = std::thread::spawn(move || stdin.write_all(request.as_bytes()));
+
+    let mut output = Vec::new();
+    child
+        .stdout
+        .take()
+        .ok_or("cannot read from git")?
+        .read_to_end(&mut output)
+        .map_err(|e| e.to_string())?;
+    let _ = writer.join();
+    child.wait().map_err(|e| e.to_string())?;
+
+    // Each object is `<oid> <type> <size>\n<contents>\n`, or `<oid> missing\n`
+    let mut contents = Vec::with_capacity(blobs.len());
+    let mut rest = output.as_slice();
+    for _ in blobs {
+        let Some(end) = rest.iter().position(|byte| *byte == b'\n') else {
+            break;
+        };
+        let header = String::from_utf8_lossy(&rest[..end]);
+        rest = &rest[end + 1..];
+        let size = header
+            .rsplit(' ')
+            .next()
+            .and_then(|size| size.parse::<usize>().ok());
+        match size {
+            Some(size) if !header.ends_with(" missing") && size <= rest.len() => {
+                contents.push(rest[..size].to_vec());
+                rest = rest.get(size + 1..).unwrap_or_default();
             }
+            _ => contents.push(Vec::new()),
         }
-        Err(_) => Ok(0),
     }
+    contents.resize(blobs.len(), Vec::new());
+    Ok(contents)
 }
@@ -0,0 +1,878 @@
+use std::path::Path;
+
+/// Comment and string syntax of a language: enough to tell code from comments
+/// and literals, not to parse it.
+pub struct Language {
+    pub name: &'static str,
+    pub extensions: &'static [&'static str],
+    /// Whole file names, for files without a telling extension.
+    pub file_names: &'static [&'static str],
+    /// Interpreters named by a `#!` line, without version suffixes.
+    pub interpreters: &'static [&'static str],
+    pub line_comments: &'static [&'static str],
+    /// Opening and closing delimiters.
+    pub block_comments: &'static [(&'static str, &'static str)],
+    /// Whether block comments nest, as in Rust and Haskell.
+    pub nested_comments: bool,
+    /// Whether line comments only start a word, as `#` in shell (`$#` isn't one).
+    pub word_comments: bool,
+    /// String delimiters, tried in order, so triple quotes go before single ones.
+    pub strings: &'static [Quote],
+    pub raw_strings: RawStrings,
+    /// Whether `'` starts a char literal only when one follows (`'a'`, `'\n'`),
+    /// as it also marks Rust lifetimes and labels.
+    pub lifetimes: bool,
+    /// Whether a triple-quoted string that is a statement of its own is a
+    /// comment, as cloc counts Python docstrings.
+    pub docstrings: bool,
+}
+
+/// A kind of string literal.
+pub struct Quote {
+    pub open: &'static str,
+    pub close: &'static str,
+    /// Whether a backslash escapes the next character.
+    pub escapes: bool,
+    /// Whether the literal may span lines; otherwise an unclosed one ends with
+    /// its line.
+    pub multiline: bool,
+}
+
+/// Raw string syntax whose delimiters depend on the literal.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum RawStrings {
+    None,
+    /// `r"…"`, `r#"…"#` and `br"…"`.
+    Rust,
+    /// `R"delim(…)delim"`.
+    Cpp,
+    /// `@"…"`, with `""` for a quote.
+    CSharp,
+}
+
+const fn quote(delimiter: &'static str, escapes: bool, multiline: bool) -> Quote {
+    Quote {
+        open: delimiter,
+        close: delimiter,
+        escapes,
+        multiline,
+    }
+}
+
+const DOUBLE: Quote = quote("\"", true, false);
+const SINGLE: Quote = quote("'", true, false);
+
+const C_LIKE: Language = Language {
+    name: "",
+    extensions: &[],
+    file_names: &[],
+    interpreters: &[],
+    line_comments: &["//"],
+    block_comments: &[("/*", "*/")],
+    nested_comments: false,
+    word_comments: false,
+    strings: &[DOUBLE, SINGLE],
+    raw_strings: RawStrings::None,
+    lifetimes: false,
+    docstrings: false,
+};
+
+const HASH_COMMENTS: Language = Language {
+    name: "",
+    extensions: &[],
+    file_names: &[],
+    interpreters: &[],
+    line_comments: &["#"],
+    block_comments: &[],
+    nested_comments: false,
+    word_comments: false,
+    strings: &[DOUBLE, SINGLE],
+    raw_strings: RawStrings::None,
+    lifetimes: false,
+    docstrings: false,
+};
+
+pub static LANGUAGES: &[Language] = &[
+    Language {
+        name: "Rust",
+        extensions: &["rs"],
+        nested_comments: true,
+        strings: &[quote("\"", true, true)],
+        raw_strings: RawStrings::Rust,
+        lifetimes: true,
+        ..C_LIKE
+    },
+    Language {
+        name: "C",
+        extensions: &["c", "h"],
+        ..C_LIKE
+    },
+    Language {
+        name: "C++",
+        extensions: &["cpp", "cc", "cxx", "hpp", "hh", "hxx"],
+        raw_strings: RawStrings::Cpp,
+        ..C_LIKE
+    },
+    Language {
+        name: "C#",
+        extensions: &["cs"],
+        raw_strings: RawStrings::CSharp,
+        ..C_LIKE
+    },
+    Language {
+        name: "Java",
+        extensions: &["java"],
+        strings: &[quote("\"\"\"", true, true), DOUBLE, SINGLE],
+        ..C_LIKE
+    },
+    Language {
+        name: "Kotlin",
+        extensions: &["kt", "kts"],
+        nested_comments: true,
+        strings: &[quote("\"\"\"", false, true), DOUBLE, SINGLE],
+        ..C_LIKE
+    },
+    Language {
+        name: "Scala",
+        extensions: &["scala"],
+        nested_comments: true,
+        strings: &[quote("\"\"\"", false, true), DOUBLE, SINGLE],
+        ..C_LIKE
+    },
+    Language {
+        name: "Swift",
+        extensions: &["swift"],
+        nested_comments: true,
+        strings: &[quote("\"\"\"", true, true), DOUBLE],
+        ..C_LIKE
+    },
+    Language {
+        name: "Go",
+        extensions: &["go"],
+        strings: &[DOUBLE, SINGLE, quote("`", false, true)],
+        ..C_LIKE
+    },
+    Language {
+        name: "JavaScript",
+        extensions: &["js", "mjs", "cjs", "jsx"],
+        interpreters: &["node"],
+        strings: &[DOUBLE, SINGLE, quote("`", true, true)],
+        ..C_LIKE
+    },
+    Language {
+        name: "TypeScript",
+        extensions: &["ts", "mts", "cts", "tsx"],
+        strings: &[DOUBLE, SINGLE, quote("`", true, true)],
+        ..C_LIKE
+    },
+    Language {
+        name: "PHP",
+        extensions: &["php"],
+        interpreters: &["php"],
+        line_comments: &["//", "#"],
+        strings: &[quote("\"", true, true), quote("'", true, true)],
+        ..C_LIKE
+    },
+    Language {
+        name: "CSS",
+        extensions: &["css", "scss", "less"],
+        line_comments: &[],
+        ..C_LIKE
+    },
+    Language {
+        name: "Python",
+        extensions: &["py", "pyi"],
+        interpreters: &["python"],
+        strings: &[
+            quote("\"\"\"", true, true),
+            quote("'''", true, true),
+            DOUBLE,
+            SINGLE,
+        ],
+        docstrings: true,
+        ..HASH_COMMENTS
+    },
+    Language {
+        name: "Ruby",
+        extensions: &["rb"],
+        file_names: &["Rakefile", "Gemfile"],
+        interpreters: &["ruby"],
+        strings: &[quote("\"", true, true), quote("'", true, true)],
+        ..HASH_COMMENTS
+    },
+    Language {
+        name: "Shell",
+        extensions: &["sh", "bash", "zsh"],
+        interpreters: &["sh", "bash", "zsh", "dash", "ksh"],
+        word_comments: true,
+        strings: &[quote("\"", true, true), quote("'", false, true)],
+        ..HASH_COMMENTS
+    },
+    Language {
+        name: "Makefile",
+        extensions: &["mk"],
+        file_names: &["Makefile", "makefile", "GNUmakefile"],
+        strings: &[],
+        ..HASH_COMMENTS
+    },
+    Language {
+        name: "Dockerfile",
+        extensions: &[],
+        file_names: &["Dockerfile"],
+        strings: &[],
+        ..HASH_COMMENTS
+    },
+    Language {
+        name: "TOML",
+        extensions: &["toml"],
+        strings: &[
+            quote("\"\"\"", true, true),
+            quote("'''", false, true),
+            DOUBLE,
+            quote("'", false, false),
+        ],
+        ..HASH_COMMENTS
+    },
+    Language {
+        name: "YAML",
+        extensions: &["yml", "yaml"],
+        word_comments: true,
+        ..HASH_COMMENTS
+    },
+    Language {
+        name: "SQL",
+        extensions: &["sql"],
+        line_comments: &["--"],
+        strings: &[quote("'", false, true), quote("\"", false, false)],
+        ..C_LIKE
+    },
+    Language {
+        name: "Lua",
+        extensions: &["lua"],
+        interpreters: &["lua"],
+        line_comments: &["--"],
+        block_comments: &[("--[[", "]]")],
+        strings: &[
+            DOUBLE,
+            SINGLE,
+            Quote {
+                open: "[[",
+                close: "]]",
+                escapes: false,
+                multiline: true,
+            },
+        ],
+        ..C_LIKE
+    },
+    Language {
+        name: "Haskell",
+        extensions: &["hs"],
+        line_comments: &["--"],
+        block_comments: &[("{-", "-}")],
+        nested_comments: true,
+        strings: &[DOUBLE],
+        ..C_LIKE
+    },
+    Language {
+        name: "JSON",
+        extensions: &["json"],
+        line_comments: &[],
+        block_comments: &[],
+        strings: &[DOUBLE],
+        ..C_LIKE
+    },
+    Language {
+        name: "Markdown",
+        extensions: &["md", "markdown"],
+        line_comments: &[],
+        block_comments: &[("<!--", "-->")],
+        strings: &[],
+        ..C_LIKE
+    },
+    Language {
+        name: "HTML",
+        extensions: &["html", "htm", "xml", "svg", "vue"],
+        line_comments: &[],
+        block_comments: &[("<!--", "-->")],
+        strings: &[],
+        ..C_LIKE
+    },
+];
+
+/// The language of a file: by file name or extension, or else by the
+/// interpreter its `#!` line runs.
+pub fn detect_language(path: &Path, text: &str) -> Option<&'static Language> {
+    language_for(path).or_else(|| {
+        let interpreter = interpreter(text.lines().next()?)?;
+        LANGUAGES
+            .iter()
+            .find(|language| language.interpreters.contains(&interpreter))
+    })
+}
+
+/// The program a `#!` line runs, looking through `env` and dropping versions:
+/// `#!/usr/bin/env -S python3.12 -u` runs `python`.
+fn interpreter(line: &str) -> Option<&str> {
+    let mut words = line.strip_prefix("#!")?.split_whitespace();
+    let mut program = words.next()?.rsplit('/').next()?;
+    if program == "env" {
+        program = words.find(|word| !word.starts_with('-') && !word.contains('='))?;
+    }
+    Some(program.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.'))
+}
+
+/// The language of `path`, by file name or extension.
+pub fn language_for(path: &Path) -> Option<&'static Language> {
+    let file_name = path.file_name()?.to_str()?;
+    let extension = path.extension().and_then(|s| s.to_str());
+    LANGUAGES
+        .iter()
+        .find(|language| language.file_names.contains(&file_name))
+        .or_else(|| {
+            let extension = extension?;
+            LANGUAGES
+                .iter()
+                .find(|language| language.extensions.contains(&extension))
+        })
+}
+
+/// What a piece of a line is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Segment {
+    Code,
+    Comment,
+    /// A string or char literal, delimiters included.
+    Literal,
+}
+
+/// What a whole line holds, the way line counters such as cloc classify it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineKind {
+    Blank,
+    /// Only comments (and whitespace).
+    Comment,
+    /// Only code and literals.
+    Code,
+    /// Code followed or preceded by a comment.
+    Mixed,
+}
+
+impl LineKind {
+    /// Whether the line has any code, as cloc counts code lines.
+    pub fn has_code(self) -> bool {
+        matches!(self, LineKind::Code | LineKind::Mixed)
+    }
+}
+
+/// One line of source split into code, comments and literals.
+#[derive(Debug, Default)]
+pub struct Line<'a> {
+    pub pieces: Vec<(Segment, &'a str)>,
+}
+
+impl<'a> Line<'a> {
+    /// The line in `text` that `spans` from [`lex`] describe.
+    pub fn of(text: &'a str, spans: &LineSpans) -> Self {
+        Line {
+            pieces: spans
+                .iter()
+                .map(|&(segment, start, end)| (segment, &text[start..end]))
+                .collect(),
+        }
+    }
+
+    pub fn kind(&self) -> LineKind {
+        let has = |segment: Segment| {
+            self.pieces
+                .iter()
+                .any(|(kind, text)| *kind == segment && !text.trim().is_empty())
+        };
+        let code = has(Segment::Code) || has(Segment::Literal);
+        match (code, has(Segment::Comment)) {
+            (true, true) => LineKind::Mixed,
+            (true, false) => LineKind::Code,
+            (false, true) => LineKind::Comment,
+            (false, false) => LineKind::Blank,
+        }
+    }
+
+    /// The line without its comments.
+    pub fn code(&self) -> String {
+        self.pieces
+            .iter()
+            .filter(|(kind, _)| *kind != Segment::Comment)
+            .map(|(_, text)| *text)
+            .collect()
+    }
+
+    /// The line without comments, each literal reduced to `""`, so keywords,
+    /// brackets and operators in text don't count as code.
+    pub fn logic(&self) -> String {
+        let mut logic = String::new();
+        for (kind, text) in &self.pieces {
+            match kind {
+                Segment::Code => logic.push_str(text),
+                Segment::Comment => {}
+                Segment::Literal => logic.push_str("\"\""),
+            }
+        }
+        logic
+    }
+}
+
+/// A line's pieces as `(segment, start, end)` byte ranges into the lexed text.
+pub type LineSpans = Vec<(Segment, usize, usize)>;
+
+/// Splits `text` into lines of code, comments and literals by `language`'s
+/// rules, as ranges that [`Line::of`] turns back into text. Without a language
+/// every line is code. Yields one line per `str::lines` line.
+pub fn lex(text: &str, language: Option<&Language>) -> Vec<LineSpans> {
+    let mut spans = match language {
+        Some(language) => Lexer::new(text, language).spans(),
+        None => vec![(Segment::Code, 0, text.len())],
+    };
+    if language.is_some_and(|language| language.docstrings) {
+        mark_docstrings(text, &mut spans);
+    }
+
+    let mut lines = vec![LineSpans::new()];
+    for (segment, start, end) in spans {
+        let mut piece_start = start;
+        for (i, piece) in text[start..end].split('\n').enumerate() {
+            if i > 0 {
+                lines.push(LineSpans::new());
+            }
+            let piece_end = piece_start + piece.strip_suffix('\r').unwrap_or(piece).len();
+            if piece_end > piece_start {
+                lines
+                    .last_mut()
+                    .unwrap()
+                    .push((segment, piece_start, piece_end));
+            }
+            piece_start += piece.len() + 1;
+        }
+    }
+    // A final newline ends the last line rather than starting another
+    if text.is_empty() || text.ends_with('\n') {
+        lines.pop();
+    }
+    lines
+}
+
+enum State {
+    Code,
+    LineComment,
+    BlockComment {
+        close: &'static str,
+        depth: usize,
+        open: &'static str,
+    },
+    Quoted(&'static Quote),
+    /// A literal ending at a fixed delimiter without escapes, such as a raw string.
+    Raw(String),
+    /// A char literal, taken whole as it opens.
+    Char,
+}
+
+struct Lexer<'a> {
+    text: &'a str,
+    language: &'a Language,
+    position: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(text: &'a str, language: &'a Language) -> Self {
+        Lexer {
+            text,
+            language,
+            position: 0,
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.text[self.position..]
+    }
+
+    fn previous_char(&self) -> Option<char> {
+        self.text[..self.position].chars().next_back()
+    }
+
+    /// Moves past the next character, or `len` bytes of a matched delimiter.
+    fn advance(&mut self, len: usize) {
+        self.position += len.max(self.rest().chars().next().map_or(1, char::len_utf8));
+    }
+
+    /// `(segment, start, end)` byte ranges covering the whole text in order.
+    fn spans(mut self) -> Vec<(Segment, usize, usize)> {
+        let mut spans: Vec<(Segment, usize, usize)> = Vec::new();
+        let mut state = State::Code;
+        let mut start = 0;
+
+        while self.position < self.text.len() {
+            let (next, len) = match &mut state {
+                State::Code => match self.open() {
+                    Some((next, len)) => (Some(next), len),
+                    None => (None, 0),
+                },
+                State::LineComment => {
+                    if self.rest().starts_with('\n') {
+                        (Some(State::Code), 0)
+                    } else {
+                        (None, 0)
+                    }
+                }
+                State::BlockComment { close, depth, open } => {
+                    if self.rest().starts_with(*close) {
+                        *depth -= 1;
+                        if *depth == 0 {
+                            self.position += close.len();
+                            (Some(State::Code), 0)
+                        } else {
+                            (None, close.len())
+                        }
+                    } else if self.language.nested_comments && self.rest().starts_with(*open) {
+                        *depth += 1;
+                        (None, open.len())
+                    } else {
+                        (None, 0)
+                    }
+                }
+                State::Quoted(quote) => {
+                    let rest = self.rest();
+                    if quote.escapes && rest.starts_with('\\') {
+                        self.position += 1;
+                        (None, 0)
+                    } else if rest.starts_with(quote.close) {
+                        self.position += quote.close.len();
+                        (Some(State::Code), 0)
+                    } else if !quote.multiline && rest.starts_with('\n') {
+                        (Some(State::Code), 0)
+                    } else {
+                        (None, 0)
+                    }
+                }
+                State::Raw(close) => {
+                    if self.rest().starts_with(close.as_str()) {
+                        self.position += close.len();
+                        (Some(State::Code), 0)
+                    } else {
+                        (None, 0)
+                    }
+                }
+                State::Char => (Some(State::Code), 0),
+            };
+
+            match next {
+                // Entering a comment or literal from code: the delimiter belongs to it
+                Some(next) if matches!(state, State::Code) => {
+                    push(&mut spans, Segment::Code, start, self.position);
+                    start = self.position;
+                    self.position += len;
+                    state = next;
+                }
+                // Leaving one: the closing delimiter has already been consumed
+                Some(next) => {
+                    let segment = match state {
+                        State::LineComment | State::BlockComment { .. } => Segment::Comment,
+                        _ => Segment::Literal,
+                    };
+                    push(&mut spans, segment, start, self.position);
+                    start = self.position;
+                    state = next;
+                }
+                None => self.advance(len),
+            }
+        }
+
+        let segment = match state {
+            State::Code => Segment::Code,
+            State::LineComment | State::BlockComment { .. } => Segment::Comment,
+            State::Quoted(_) | State::Raw(_) | State::Char => Segment::Literal,
+        };
+        push(&mut spans, segment, start, self.text.len());
+        spans
+    }
+
+    /// A comment or literal opening at the current position, with the length of
+    /// its opening delimiter.
+    fn open(&self) -> Option<(State, usize)> {
+        let rest = self.rest();
+        let language = self.language;
+
+        for &(open, close) in language.block_comments {
+            if rest.starts_with(open) {
+                return Some((State::BlockComment { close, depth: 1, open }, open.len()));
+            }
+        }
+        let at_word_start = self.previous_char().is_none_or(char::is_whitespace);
+        for comment in language.line_comments {
+            if rest.starts_with(comment) && (!language.word_comments || at_word_start) {
+                return Some((State::LineComment, comment.len()));
+            }
+        }
+        if let Some(raw) = self.raw_string() {
+            return Some(raw);
+        }
+        if language.lifetimes && rest.starts_with('\'') {
+            return self.char_literal().map(|len| (State::Char, len));
+        }
+        language
+            .strings
+            .iter()
+            .find(|quote| rest.starts_with(quote.open))
+            .map(|quote| (State::Quoted(quote), quote.open.len()))
+    }
+
+    /// A raw string opening here, closing with the returned delimiter.
+    fn raw_string(&self) -> Option<(State, usize)> {
+        let rest = self.rest();
+        // A prefix glued to an identifier (`bar"`) isn't one
+        if self
+            .previous_char()
+            .is_some_and(|c| c.is_alphanumeric() || c == '_')
+        {
+            return None;
+        }
+
+        match self.language.raw_strings {
+            RawStrings::None => None,
+            RawStrings::Rust => {
+                let after_prefix = rest
+                    .strip_prefix("br")
+                    .or_else(|| rest.strip_prefix("cr"))
+                    .or_else(|| rest.strip_prefix('r'))?;
+                let hashes = after_prefix.len() - after_prefix.trim_start_matches('#').len();
+                after_prefix[hashes..].starts_with('"').then(|| {
+                    let open = rest.len() - after_prefix.len() + hashes + 1;
+                    (State::Raw(format!("\"{}", "#".repeat(hashes))), open)
+                })
+            }
+            RawStrings::Cpp => {
+                let after_prefix = ["u8R\"", "uR\"", "UR\"", "LR\"", "R\""]
+                    .iter()
+                    .find_map(|prefix| rest.strip_prefix(prefix))?;
+                let delimiter_len = after_prefix.find('(')?;
+                let delimiter = &after_prefix[..delimiter_len];
+                (delimiter.len() <= 16 && !delimiter.contains([' ', '\\', ')', '"'])).then(|| {
+                    let open = rest.len() - after_prefix.len() + delimiter_len + 1;
+                    (State::Raw(format!("){delimiter}\"")), open)
+                })
+            }
+            // `""` inside is a quote, which reads as the literal closing and
+            // reopening right away
+            RawStrings::CSharp => rest
+                .starts_with("@\"")
+                .then_some((State::Quoted(&VERBATIM), 2)),
+        }
+    }
+
+    /// The length of a char literal starting at the `'` here, if it is one
+    /// rather than a lifetime or label.
+    fn char_literal(&self) -> Option<usize> {
+        let body = &self.rest()[1..];
+        let mut chars = body.char_indices();
+        let (_, first) = chars.next()?;
+        let end = if first == '\\' {
+            // `'\n'`, `'\''`, `'\u{1F600}'`: the literal closes within a few chars
+            let (escaped, _) = chars.next()?;
+            body[escaped..]
+                .char_indices()
+                .skip(1)
+                .take(10)
+                .find(|(_, c)| *c == '\'')
+                .map(|(i, _)| escaped + i)?
+        } else {
+            let (i, next) = chars.next()?;
+            (next == '\'').then_some(i)?
+        };
+        Some(end + 2)
+    }
+}
+
+/// Turns the triple-quoted literals that stand alone on their lines, apart from
+/// a trailing comment, into comments.
+fn mark_docstrings(text: &str, spans: &mut [(Segment, usize, usize)]) {
+    for (segment, start, end) in spans.iter_mut() {
+        let literal = &text[*start..*end];
+        if *segment != Segment::Literal
+            || !(literal.starts_with("\"\"\"") || literal.starts_with("'''"))
+        {
+            continue;
+        }
+        let before = text[..*start].rsplit('\n').next().unwrap_or_default();
+        let after = text[*end..].split('\n').next().unwrap_or_default().trim();
+        if before.trim().is_empty() && (after.is_empty() || after.starts_with('#')) {
+            *segment = Segment::Comment;
+        }
+    }
+}
+
+static VERBATIM: Quote = quote("\"", false, true);
+
+/// Adds a non-empty span, merging it into the previous one of the same kind.
+fn push(spans: &mut Vec<(Segment, usize, usize)>, segment: Segment, start: usize, end: usize) {
+    if start == end {
+        return;
+    }
+    match spans.last_mut() {
+        Some((last, _, last_end)) if *last == segment && *last_end == start => *last_end = end,
+        _ => spans.push((segment, start, end)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Segment::{Code, Comment, Literal};
+
+    /// Each line of `text` lexed as the language of `file`, as its pieces.
+    fn pieces<'a>(file: &str, text: &'a str) -> Vec<Vec<(Segment, &'a str)>> {
+        let language = language_for(Path::new(file));
+        assert!(language.is_some(), "no language for {file}");
+        lex(text, language)
+            .iter()
+            .map(|spans| Line::of(text, spans).pieces)
+            .collect()
+    }
+
+    fn kinds(file: &str, text: &str) -> Vec<LineKind> {
+        lex(text, language_for(Path::new(file)))
+            .iter()
+            .map(|spans| Line::of(text, spans).kind())
+            .collect()
+    }
+
+    #[test]
+    fn python_hash_comments() {
+        assert_eq!(
+            pieces("a.py", "# setup\nx = 1  # one\n"),
+            [
+                vec![(Comment, "# setup")],
+                vec![(Code, "x = 1  "), (Comment, "# one")]
+            ]
+        );
+        assert_eq!(
+            kinds("a.py", "# setup\nx = 1  # one\n\n"),
+            [LineKind::Comment, LineKind::Mixed, LineKind::Blank]
+        );
+    }
+
+    #[test]
+    fn python_triple_quoted_strings() {
+        let text = "s = \"\"\"a # not a comment\n'quoted' \"x\"\n\"\"\"\ny = 2\n";
+        assert_eq!(
+            pieces("a.py", text),
+            [
+                vec![(Code, "s = "), (Literal, "\"\"\"a # not a comment")],
+                vec![(Literal, "'quoted' \"x\"")],
+                vec![(Literal, "\"\"\"")],
+                vec![(Code, "y = 2")],
+            ]
+        );
+        assert_eq!(kinds("a.py", text), [LineKind::Code; 4]);
+    }
+
+    #[test]
+    fn python_docstrings_are_comments() {
+        let text = "def f():\n    \"\"\"Doc.\n\n    More.\"\"\"\n    return \"\"\"x\"\"\"\n";
+        assert_eq!(
+            pieces("a.py", text)[1..],
+            [
+                vec![(Code, "    "), (Comment, "\"\"\"Doc.")],
+                vec![],
+                vec![(Comment, "    More.\"\"\"")],
+                vec![(Code, "    return "), (Literal, "\"\"\"x\"\"\"")],
+            ]
+        );
+    }
+
+    #[test]
+    fn rust_lifetimes_are_code_and_chars_literals() {
+        assert_eq!(
+            pieces("a.rs", "fn f<'a>(s: &'a str) -> char { 'x' }\n"),
+            [vec![
+                (Code, "fn f<'a>(s: &'a str) -> char { "),
+                (Literal, "'x'"),
+                (Code, " }"),
+            ]]
+        );
+        assert_eq!(
+            pieces("a.rs", "let c = '\\''; 'outer: loop {}\n"),
+            [vec![
+                (Code, "let c = "),
+                (Literal, "'\\''"),
+                (Code, "; 'outer: loop {}"),
+            ]]
+        );
+    }
+
+    #[test]
+    fn rust_raw_strings() {
+        assert_eq!(
+            pieces("a.rs", "let s = r#\"say \"hi\" // no\"#; // yes\n"),
+            [vec![
+                (Code, "let s = "),
+                (Literal, "r#\"say \"hi\" // no\"#"),
+                (Code, "; "),
+                (Comment, "// yes"),
+            ]]
+        );
+    }
+
+    #[test]
+    fn rust_nested_block_comments() {
+        let text = "/* outer /* inner */ still */ x\n";
+        assert_eq!(
+            pieces("a.rs", text),
+            [vec![
+                (Comment, "/* outer /* inner */ still */"),
+                (Code, " x"),
+            ]]
+        );
+        // C comments don't nest: the first `*/` closes
+        assert_eq!(
+            pieces("a.c", text),
+            [vec![
+                (Comment, "/* outer /* inner */"),
+                (Code, " still */ x"),
+            ]]
+        );
+        assert_eq!(
+            kinds("a.rs", "/* a\n/* b */\nc */\n"),
+            [LineKind::Comment; 3]
+        );
+    }
+
+    #[test]
+    fn go_backtick_strings() {
+        // A backslash doesn't escape in a raw string
+        assert_eq!(
+            pieces("a.go", "dir := `C:\\tmp\\` // windows\n"),
+            [vec![
+                (Code, "dir := "),
+                (Literal, "`C:\\tmp\\`"),
+                (Code, " "),
+                (Comment, "// windows"),
+            ]]
+        );
+        assert_eq!(
+            pieces("a.go", "s := `a\n// b`\n"),
+            [
+                vec![(Code, "s := "), (Literal, "`a")],
+                vec![(Literal, "// b`")],
+            ]
+        );
+    }
+
+    #[test]
+    fn shell_hash_only_comments_at_word_start() {
+        assert_eq!(
+            pieces("a.sh", "echo $# ${#args} # count\n"),
+            [vec![(Code, "echo $# ${#args} "), (Comment, "# count")]]
+        );
+        assert_eq!(
+            kinds("a.sh", "#!/bin/sh\necho $#\n"),
+            [LineKind::Comment, LineKind::Code]
+        );
+    }
+}
@@ -0,0 +1,20 @@
+//! Code analysis building blocks behind the `madu` CLI: per-file content metrics,
+//! git history metrics and the [`Analyzer`] that walks a tree and ranks the results.
+
+pub mod analysis;
+pub mod analyzer;
+pub mod baseline;
+pub mod cache;
+pub mod coupling;
+pub mod file_utils;
+pub mod functions;
+pub mod gates;
+pub mod git;
+pub mod lexer;
+pub mod metrics;
+pub mod output;
+pub mod score;
+pub mod trend;
+
+pub use analyzer::{Analyzer, Report, Row};
+pub use metrics::{Aggregation, Direction, Metric, MetricContext, MetricOutput};
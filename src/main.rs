@@ -1,370 +1,446 @@
-mod analysis;
 mod args;
+mod config;
 mod display;
-mod file_utils;
-mod git;
 mod watch;
 
-use analysis::{
-    analyze_emojis, calculate_code_density, calculate_complexity, calculate_duplication_percentage,
-    calculate_max_indent_level,
+use args::{Args, Command, DiffArgs, OutputFormat, TrendArgs, TrendBy};
+use display::{
+    print_changes, print_couplings, print_functions, print_report, print_trend, signed,
+    write_changes, write_couplings, write_functions, write_report, write_trend,
 };
-use args::Args;
-use clap::Parser;
-use display::print_colored_count;
-use file_utils::{
-    count_lines, count_nonwhitespace_chars, format_size, get_file_size, is_noise_file,
-    should_include,
-};
-use git::{
-    calculate_churn, calculate_file_age_days, calculate_isolation_percentage,
-    calculate_ownership_percentage, calculate_rhythm_score, get_primary_author,
-};
-use ignore::WalkBuilder;
-use rayon::prelude::*;
-use std::collections::HashMap;
+use mad_useful::analyzer::Row;
+use mad_useful::file_utils::relative_key;
+use mad_useful::Analyzer;
+use mad_useful::baseline::{Baseline, ChangeKind};
+use mad_useful::cache::{clear_cache, default_cache_dir};
+use mad_useful::coupling::change_coupling;
+use mad_useful::gates::{GATE_FAILURE_EXIT_CODE, Gate, evaluate_gates};
+use mad_useful::git::{ChurnKind, resolve_date};
+use mad_useful::metrics::{BusFactor, LineBusFactor, find_metric};
+use mad_useful::trend::{TrendScope, sample_commits, supports, trend};
 use std::path::{Path, PathBuf};
-use termcolor::{ColorChoice, StandardStream};
 use watch::watch_mode;
 
 fn main() {
-    let args = Args::parse();
+    let resolved = config::load_args();
+    if resolved.args.print_config {
+        resolved.print_config();
+        return;
+    }
+    let mut gates = resolved.config_gates();
+    let author_aliases = resolved.author_aliases();
+    let mut args = resolved.args;
+    args.author_aliases = author_aliases;
+    // Bus factor is reported per directory, honoring --depth
+    args.dirs |= args.bus_factor;
+    args.path = args.target_path().to_string();
+    gates.extend(args.fail_if.iter().cloned());
+
+    if args.clear_cache
+        && let Some(dir) = default_cache_dir(Path::new(&args.path))
+        && let Err(e) = clear_cache(&dir, Path::new(&args.path))
+    {
+        eprintln!("error: cannot clear cache in {}: {e}", dir.display());
+        std::process::exit(2);
+    }
+
+    if let Some(Command::Trend(trend_args)) = &args.command {
+        run_trend(&args, trend_args);
+        return;
+    }
+
+    if let Some(Command::Diff(diff_args)) = &args.command {
+        run_diff(&args, diff_args);
+        return;
+    }
 
     if let Some(interval) = args.watch {
         watch_mode(&args, interval);
         return;
     }
 
-    run_analysis(&args);
+    if args.coupling {
+        run_coupling(&args);
+        return;
+    }
+
+    if args.functions {
+        run_functions(&args);
+        return;
+    }
+
+    run_analysis(&args, &gates);
 }
 
-fn run_analysis(args: &Args) {
-    let mut stdout = StandardStream::stdout(ColorChoice::Auto);
-
-    let files: Vec<PathBuf> = WalkBuilder::new(&args.path)
-        .build()
-        .filter_map(|result| {
-            if let Ok(entry) = result {
-                let path = entry.path();
-                if path.is_file()
-                    && should_include(path, &args.include, &args.exclude)
-                    && (!args.no_noise || !is_noise_file(path))
-                {
-                    Some(path.to_path_buf())
-                } else {
-                    None
-                }
-            } else {
-                None
-            }
-        })
+fn run_trend(args: &Args, trend_args: &TrendArgs) {
+    let fail = |message: String| -> ! {
+        eprintln!("error: {message}");
+        std::process::exit(2);
+    };
+
+    let scope = match trend_args.by {
+        _ if args.dirs => TrendScope::Dir,
+        TrendBy::Total => TrendScope::Total,
+        TrendBy::Dir => TrendScope::Dir,
+        TrendBy::File => TrendScope::File,
+    };
+    let mut analyzer = analyzer_from_args(args);
+    if scope == TrendScope::Dir {
+        analyzer = analyzer.dirs(args.depth);
+    }
+    let unsupported: Vec<&str> = analyzer
+        .selected_metrics()
+        .iter()
+        .filter(|metric| !supports(**metric))
+        .map(|metric| metric.name())
         .collect();
+    if !unsupported.is_empty() {
+        fail(format!(
+            "trend can only evaluate metrics that don't need git history, not {}",
+            unsupported.join(", ")
+        ));
+    }
 
-    let results: Vec<(PathBuf, usize, String, String)> = files
-        .par_iter()
-        .filter_map(|path| {
-            let (value, emoji_info) = if args.size {
-                let file_size = get_file_size(path).unwrap_or(0);
-                (file_size, format_size(file_size))
-            } else if args.chars {
-                let char_count = count_nonwhitespace_chars(path).unwrap_or(0);
-                (char_count, String::new())
-            } else if args.indent {
-                let max_indent = calculate_max_indent_level(path).unwrap_or(0);
-                (max_indent, format!("{max_indent}↓"))
-            } else if args.isolation {
-                let isolation_pct = calculate_isolation_percentage(path).unwrap_or(0);
-                (isolation_pct, format!("{isolation_pct}%"))
-            } else if args.rhythm {
-                let rhythm_score = calculate_rhythm_score(path).unwrap_or(0);
-                (rhythm_score, format!("{rhythm_score}d"))
-            } else if args.ownership {
-                let owner_pct = calculate_ownership_percentage(path).unwrap_or(0);
-                (owner_pct, format!("{owner_pct}%"))
-            } else if args.age {
-                let days_old = calculate_file_age_days(path).unwrap_or(0);
-                (days_old, format!("{days_old}d"))
-            } else if args.duplicates {
-                let dup_pct = calculate_duplication_percentage(path, &files).unwrap_or(0);
-                (dup_pct, format!("{dup_pct}%"))
-            } else if args.emoji {
-                let info = analyze_emojis(path).unwrap_or_default();
-                (info.total, format!("{}u {}", info.unique, info.most_common))
-            } else {
-                let val = if args.density {
-                    calculate_code_density(path).unwrap_or(0)
-                } else if args.hotspots {
-                    let complexity = calculate_complexity(path).unwrap_or(1);
-                    let churn = calculate_churn(path, args.days).unwrap_or(0);
-                    complexity * churn
-                } else if args.churn {
-                    calculate_churn(path, args.days).unwrap_or(0)
-                } else if args.complexity {
-                    calculate_complexity(path).unwrap_or(0)
-                } else if args.chars {
-                    count_nonwhitespace_chars(path).unwrap_or(0)
-                } else if args.size {
-                    get_file_size(path).unwrap_or(0)
-                } else {
-                    count_lines(path).unwrap_or(0)
-                };
-                (val, String::new())
-            };
+    let path = Path::new(&args.path);
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let resolve = |date: &String| resolve_date(path, date, now).unwrap_or_else(|e| fail(e));
+    let since = args.since.as_ref().map(resolve);
+    let until = args.until.as_ref().map(resolve).unwrap_or(now);
+    let revision = args
+        .git_ref
+        .as_deref()
+        .or(args.tree_ref.as_deref())
+        .unwrap_or("HEAD");
 
-            let author = if args.blame || args.author.is_some() {
-                get_primary_author(path).unwrap_or_else(|| "unknown".to_string())
-            } else {
-                String::new()
-            };
+    let samples = sample_commits(&analyzer, revision, since, until, trend_args.every)
+        .unwrap_or_else(|e| fail(e));
+    if samples.is_empty() {
+        fail(format!("no commits of {revision} in the sampled period"));
+    }
+    let mut series = trend(&analyzer, &samples, scope).unwrap_or_else(|e| fail(e));
+    if let Some(n) = args.top {
+        series.truncate(n * analyzer.selected_metrics().len());
+    }
 
-            if let Some(filter_author) = &args.author {
-                if !author
-                    .to_lowercase()
-                    .contains(&filter_author.to_lowercase())
-                {
-                    return None;
-                }
-            }
-
-            if value > 0 {
-                Some((path.clone(), value, author, emoji_info))
-            } else {
-                None
-            }
-        })
-        .collect();
+    match args.format {
+        OutputFormat::Text => print_trend(args, &samples, &series),
+        _ => write_trend(args, &samples, &series),
+    }
+}
 
-    let mut results = results;
+fn run_diff(args: &Args, diff_args: &DiffArgs) {
+    let fail = |message: String| -> ! {
+        eprintln!("error: {message}");
+        std::process::exit(2);
+    };
 
-    // Aggregate by directory if --dirs flag is set
-    if args.dirs {
-        let mut dir_aggregates: HashMap<PathBuf, (usize, usize, Vec<String>)> = HashMap::new();
-
-        // Get base path to calculate relative depth
-        let base_path = Path::new(&args.path);
-        let base_components_count = base_path.components().count();
-
-        for (path, value, author, _extra_info) in results {
-            let mut dir = path
-                .parent()
-                .unwrap_or_else(|| Path::new("."))
-                .to_path_buf();
-
-            // If depth is specified, truncate directory path to that depth relative to base
-            if let Some(target_depth) = args.depth {
-                let components: Vec<_> = dir.components().collect();
-                let relative_depth = base_components_count + target_depth;
-                if components.len() > relative_depth {
-                    dir = components.iter().take(relative_depth).collect();
-                }
-            }
-
-            let entry = dir_aggregates.entry(dir).or_insert((0, 0, Vec::new()));
-            entry.0 += value; // Sum values
-            entry.1 += 1; // Count files
-            if !author.is_empty() && !entry.2.contains(&author) {
-                entry.2.push(author); // Collect unique authors
-            }
+    // A side that's a directory on disk is a tree to walk; anything else is a
+    // revision of the repository at the target path
+    let analyze = |side: &str| -> (Analyzer, Vec<Row>, PathBuf) {
+        if Path::new(side).is_dir() {
+            let mut side_args = args.clone();
+            side_args.path = side.to_string();
+            let analyzer = analyzer_from_args(&side_args);
+            let rows = analyzer.aggregate(analyzer.analyze(&analyzer.collect_files()));
+            let root = analyzer.path().to_path_buf();
+            return (analyzer, rows, root);
         }
 
-        results = dir_aggregates
-            .into_iter()
-            .map(|(dir, (total_value, file_count, authors))| {
-                let author_info = if authors.is_empty() {
-                    String::new()
-                } else {
-                    authors.join(",")
-                };
-                let extra_info = if args.size {
-                    format!("{} ({}f)", format_size(total_value), file_count)
-                } else if args.emoji
-                    || args.duplicates
-                    || args.age
-                    || args.ownership
-                    || args.isolation
-                    || args.rhythm
-                    || args.indent
-                {
-                    format!("{file_count}f")
-                } else {
-                    String::new()
-                };
-                (dir, total_value, author_info, extra_info)
-            })
+        let analyzer = analyzer_from_args(args);
+        let unsupported: Vec<&str> = analyzer
+            .selected_metrics()
+            .iter()
+            .filter(|metric| !supports(**metric))
+            .map(|metric| metric.name())
             .collect();
+        if !unsupported.is_empty() {
+            fail(format!(
+                "diff can only compare revisions on metrics that don't need git history, not {}",
+                unsupported.join(", ")
+            ));
+        }
+        let rows = analyzer
+            .analyze_commit(side)
+            .unwrap_or_else(|e| fail(format!("cannot analyze {side}: {e}")));
+        let rows = analyzer.aggregate(rows);
+        let root = analyzer.path().to_path_buf();
+        (analyzer, rows, root)
+    };
+
+    let (_, before_rows, before_root) = analyze(&diff_args.before);
+    let (analyzer, after_rows, after_root) = analyze(&diff_args.after);
+    let metrics = analyzer.selected_metrics();
+
+    let mut changes = Baseline::from_rows(&metrics, &before_rows, &before_root).compare(
+        &metrics,
+        &after_rows,
+        &after_root,
+    );
+    // Both sides share relative paths, so show those rather than either root
+    for change in &mut changes {
+        change.path = PathBuf::from(relative_key(&change.path, &after_root));
+    }
+    let min_value = args.min_value.unwrap_or(0);
+    changes.retain(|change| change.kind != ChangeKind::New || change.after >= min_value);
+    if let Some(n) = args.top {
+        changes.truncate(n);
     }
 
-    let has_filters = args.top.is_some()
-        || args.skip.is_some()
-        || args.min_value.is_some()
-        || args.threshold.is_some()
-        || args.age
-        || args.ownership
-        || args.duplicates
-        || args.complexity
-        || args.churn
-        || args.hotspots
-        || args.density
-        || args.isolation
-        || args.rhythm
-        || args.indent
-        || args.chars
-        || args.dirs
-        || args.depth.is_some()
-        || args.size;
-
-    if has_filters {
-        results.sort_by(|a, b| b.1.cmp(&a.1));
-    } else {
-        results.sort_by(|a, b| a.0.cmp(&b.0));
+    if args.format != OutputFormat::Text {
+        write_changes(args, &changes);
+        return;
+    }
+    print_changes(args, &changes);
+    let before_totals = analyzer.totals(&before_rows);
+    let after_totals = analyzer.totals(&after_rows);
+    for ((metric, before), after) in metrics.iter().zip(before_totals).zip(after_totals) {
+        println!(
+            "Δ{} {} ({before} → {after} {})",
+            signed(after as i64 - before as i64),
+            metric.name(),
+            metric.unit()
+        );
     }
+}
+
+fn run_coupling(args: &Args) {
+    let analyzer = analyzer_from_args(args);
+    let history = analyzer.load_history().unwrap_or_else(|e| {
+        eprintln!("error: cannot read git history: {e}");
+        std::process::exit(2);
+    });
 
-    if let Some(min_val) = args.min_value {
-        results.retain(|(_, value, _, _)| *value >= min_val);
+    let units: Vec<_> = analyzer
+        .collect_files()
+        .into_iter()
+        .map(|file| {
+            let unit = if args.dirs {
+                analyzer.dir_of(&file)
+            } else {
+                file.clone()
+            };
+            (file, unit)
+        })
+        .collect();
+    // A single shared commit is coincidence rather than coupling
+    let min_support = args.min_value.unwrap_or(2);
+    let mut couplings = change_coupling(history, &units, args.days, min_support);
+    if let Some(n) = args.top {
+        couplings.truncate(n);
     }
 
-    if let Some(threshold_pct) = args.threshold {
-        if let Some(max_value) = results.iter().map(|(_, v, _, _)| v).max() {
-            let threshold = (*max_value * threshold_pct as usize) / 100;
-            results.retain(|(_, value, _, _)| *value >= threshold);
-        }
+    match args.format {
+        OutputFormat::Text => print_couplings(args, &couplings),
+        _ => write_couplings(args, &couplings),
     }
+}
 
-    if let Some(skip_n) = args.skip {
-        if skip_n < results.len() {
-            results = results.into_iter().skip(skip_n).collect();
-        } else {
-            results.clear();
-        }
+fn run_functions(args: &Args) {
+    let analyzer = analyzer_from_args(args);
+    let mut functions = analyzer.functions(&analyzer.collect_files());
+    if let Some(min) = args.min_value {
+        functions.retain(|function| function.complexity >= min);
+    }
+    if let Some(n) = args.top {
+        functions.truncate(n);
     }
 
-    if let Some(top_n) = args.top {
-        results.truncate(top_n);
-    }
-
-    let total: usize = results.iter().map(|(_, count, _, _)| count).sum();
-    let file_count = results.len();
-
-    let max_lines_per_file = args.max_lines.unwrap_or_else(|| {
-        let default_max = if args.size {
-            1000000
-        } else if args.chars {
-            50000
-        } else if args.indent {
-            20
-        } else if args.isolation {
-            100
-        } else if args.rhythm {
-            50
-        } else if args.ownership {
-            100
-        } else if args.age {
-            365
-        } else if args.duplicates {
-            50
-        } else if args.emoji {
-            10
-        } else if args.density {
-            80
-        } else if args.hotspots {
-            200
-        } else if args.churn {
-            50
-        } else if args.complexity {
-            20
-        } else {
-            1000
-        };
-        results
-            .iter()
-            .map(|(_, count, _, _)| *count)
-            .max()
-            .unwrap_or(default_max)
-    });
+    match args.format {
+        OutputFormat::Text => print_functions(args, &functions),
+        _ => write_functions(args, &functions),
+    }
+}
 
-    if args.summary {
-        let mut by_ext: HashMap<String, (usize, usize)> = HashMap::new();
-        for (path, count, _, _) in &results {
-            let ext = path
-                .extension()
-                .and_then(|s| s.to_str())
-                .unwrap_or("no_ext")
-                .to_string();
-            let entry = by_ext.entry(ext).or_insert((0, 0));
-            entry.0 += count;
-            entry.1 += 1;
-        }
+fn run_analysis(args: &Args, gates: &[Gate]) {
+    let mut analyzer = analyzer_from_args(args);
+    for gate in gates {
+        analyzer = analyzer.gate_metric(gate.metric);
+    }
+
+    let files = analyzer.collect_files();
+    let rows = analyzer.analyze(&files);
+    // Gates see every analyzed file, not just the rows left after --top/--threshold
+    let violations = evaluate_gates(gates, &analyzer.computed_metrics(), &rows, analyzer.path());
+    let rows = analyzer.visible(rows);
+    let metrics = analyzer.selected_metrics();
+
+    // Load before saving so both flags can name the same file
+    let baseline = args.compare_baseline.as_ref().map(|path| {
+        Baseline::load(Path::new(path)).unwrap_or_else(|e| {
+            eprintln!("error: cannot read baseline {path}: {e}");
+            std::process::exit(2);
+        })
+    });
+    if let Some(path) = &args.save_baseline
+        && let Err(e) = Baseline::from_rows(&metrics, &rows, analyzer.path()).save(Path::new(path))
+    {
+        eprintln!("error: cannot write baseline {path}: {e}");
+        std::process::exit(2);
+    }
 
-        let mut sorted_exts: Vec<_> = by_ext.into_iter().collect();
-        sorted_exts.sort_by(|a, b| b.1.0.cmp(&a.1.0));
+    let mut regressed = false;
+    if let Some(baseline) = baseline {
+        let mut changes = baseline.compare(&metrics, &rows, analyzer.path());
+        let min_value = args.min_value.unwrap_or(0);
+        changes.retain(|change| change.kind != ChangeKind::New || change.after >= min_value);
+        regressed = changes
+            .iter()
+            .any(|change| change.kind == ChangeKind::Regressed);
 
-        for (ext, (lines, files)) in sorted_exts {
-            print_colored_count(&mut stdout, lines, 1, max_lines_per_file, args.no_color);
-            println!(" {ext} ({files} files)");
+        if args.format == OutputFormat::Text {
+            print_changes(args, &changes);
+        } else {
+            write_changes(args, &changes);
         }
     } else {
-        for (path, count, author, extra_info) in results {
-            print_colored_count(&mut stdout, count, 1, max_lines_per_file, args.no_color);
-            if (args.emoji
-                || args.duplicates
-                || args.age
-                || args.ownership
-                || args.isolation
-                || args.rhythm
-                || args.indent
-                || args.dirs
-                || args.size)
-                && !extra_info.is_empty()
-            {
-                if args.blame && !author.is_empty() {
-                    println!(" {} [{}] ({})", path.display(), author, extra_info);
-                } else {
-                    println!(" {} ({})", path.display(), extra_info);
-                }
-            } else if args.blame && !author.is_empty() {
-                println!(" {} [{}]", path.display(), author);
-            } else {
-                println!(" {}", path.display());
-            }
+        let report = analyzer.report(rows);
+        if args.format == OutputFormat::Text {
+            print_report(args, &report, |_| String::new());
+        } else {
+            write_report(args, report);
         }
     }
 
-    print_colored_count(
-        &mut stdout,
-        total,
-        file_count,
-        max_lines_per_file,
-        args.no_color,
-    );
+    if !violations.is_empty() {
+        eprintln!("madu: {} quality gate violation(s)", violations.len());
+        for violation in &violations {
+            eprintln!("  {violation}");
+        }
+    }
+    if args.ratchet && regressed {
+        eprintln!("madu: files regressed against the baseline (--ratchet)");
+    }
+    if !violations.is_empty() || (args.ratchet && regressed) {
+        std::process::exit(GATE_FAILURE_EXIT_CODE);
+    }
+}
+
+/// Translates command-line flags into the library's analyzer configuration.
+pub fn analyzer_from_args(args: &Args) -> Analyzer {
+    let flags = [
+        (args.size, "size"),
+        (args.chars, "chars"),
+        (args.indent, "indent"),
+        (args.isolation, "isolation"),
+        (args.rhythm, "rhythm"),
+        (args.ownership, "ownership"),
+        (args.blame_lines, "line-ownership"),
+        (args.bus_factor && !args.blame_lines, "bus-factor"),
+        (args.bus_factor && args.blame_lines, "line-bus-factor"),
+        (args.age, "age"),
+        (args.duplicates, "duplicates"),
+        (args.emoji, "emoji"),
+        (args.density, "density"),
+        (args.hotspots, "hotspots"),
+        (args.churn, ChurnKind::from(args.churn_by).metric_name()),
+        (args.complexity, "complexity"),
+        (args.loc, "code"),
+        (args.loc, "comment"),
+        (args.loc, "blank"),
+        (args.loc, "mixed"),
+    ];
+
+    let mut analyzer = Analyzer::new(&args.path)
+        .no_noise(args.no_noise)
+        .days(args.days)
+        .hotspot_churn(args.churn_by.into())
+        .authors(args.blame)
+        .former_paths(args.show_renames)
+        .authors_by_email(args.author_email);
+
+    let requested = flags
+        .iter()
+        .filter(|(enabled, _)| *enabled)
+        .map(|(_, name)| *name)
+        .chain(args.metric.iter().map(String::as_str));
+    for name in requested {
+        if let Some(metric) = find_metric(name) {
+            analyzer = analyzer.metric(metric);
+        }
+    }
+    // Bus factor ranks directories even when line ownership is shown before it
+    if args.bus_factor {
+        analyzer = analyzer.rank_by(if args.blame_lines {
+            &LineBusFactor
+        } else {
+            &BusFactor
+        });
+    }
+
+    for (alias, identity) in &args.author_aliases {
+        analyzer = analyzer.author_alias(alias, identity);
+    }
+    for pattern in &args.exclude_author {
+        analyzer = analyzer.exclude_author(pattern.clone());
+    }
+    for pattern in &args.include {
+        analyzer = analyzer.include(pattern.clone());
+    }
+    for pattern in &args.exclude {
+        analyzer = analyzer.exclude(pattern.clone());
+    }
+    if let Some(author) = &args.author {
+        analyzer = analyzer.author_filter(author);
+    }
+    if let Some(score) = &args.score {
+        analyzer = analyzer.hotspot_score(score.clone());
+    }
+    if let Some(date) = &args.since {
+        analyzer = analyzer.since(date);
+    }
+    if let Some(date) = &args.until {
+        analyzer = analyzer.until(date);
+    }
+    if let Some(revisions) = args
+        .rev_range
+        .as_ref()
+        .or(args.git_ref.as_ref())
+        .or(args.tree_ref.as_ref())
+    {
+        analyzer = analyzer.revisions(revisions);
+    }
+    if let Some(revision) = &args.tree_ref {
+        analyzer = analyzer.tree(revision);
+    }
+    if let Err(e) = analyzer.load_tree() {
+        eprintln!("error: cannot read the files of {}: {e}", analyzer.path().display());
+        std::process::exit(2);
+    }
+    // An explicit range that git rejects should fail loudly rather than read as
+    // a quiet history
+    if (args.since.is_some()
+        || args.until.is_some()
+        || args.rev_range.is_some()
+        || args.git_ref.is_some()
+        || args.tree_ref.is_some())
+        && let Err(e) = analyzer.load_history()
+    {
+        eprintln!("error: cannot read git history: {e}");
+        std::process::exit(2);
+    }
+    if !args.no_cache
+        && let Some(dir) = default_cache_dir(Path::new(&args.path))
+    {
+        analyzer = analyzer.cache(dir);
+    }
     if args.dirs {
-        println!(" total dirs");
-    } else if args.size {
-        println!(" total bytes");
-    } else if args.chars {
-        println!(" total chars");
-    } else if args.indent {
-        println!(" max indent depth");
-    } else if args.isolation {
-        println!(" avg isolation %");
-    } else if args.rhythm {
-        println!(" avg rhythm score");
-    } else if args.ownership {
-        println!(" avg ownership %");
-    } else if args.age {
-        println!(" avg age (days)");
-    } else if args.duplicates {
-        println!(" avg duplication %");
-    } else if args.emoji {
-        println!(" total emojis");
-    } else if args.density {
-        println!(" total density score");
-    } else if args.hotspots {
-        println!(" total hotspot score");
-    } else if args.churn {
-        println!(" total changes");
-    } else if args.complexity {
-        println!(" total complexity");
-    } else {
-        println!(" total");
+        analyzer = analyzer.dirs(args.depth);
+    }
+    if let Some(n) = args.top {
+        analyzer = analyzer.top(n);
+    }
+    if let Some(n) = args.skip {
+        analyzer = analyzer.skip(n);
     }
+    if let Some(value) = args.min_value {
+        analyzer = analyzer.min_value(value);
+    }
+    if let Some(percent) = args.threshold {
+        analyzer = analyzer.threshold(percent);
+    }
+
+    analyzer
 }
@@ -0,0 +1,1116 @@
+use crate::analysis::{
+    DuplicationIndex, FileChunks, analyze_emojis, calculate_code_density, calculate_complexity,
+    calculate_max_indent_level,
+};
+use crate::file_utils::{
+    SourceFile, count_lines, count_nonwhitespace_chars, format_size, get_file_size,
+};
+use crate::functions::complexity_stats;
+use crate::git::{
+    ChurnKind, GitHistory, author_counts, bus_factor_authors, calculate_churn,
+    calculate_file_age_days, calculate_isolation_percentage, calculate_line_churn,
+    calculate_ownership_percentage, calculate_rhythm_score,
+};
+use crate::output::Extras;
+use crate::score::ScoreFormula;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// How per-file values roll up into directory, extension and total rows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Aggregation {
+    Sum,
+    Avg,
+    Max,
+}
+
+impl Aggregation {
+    pub fn apply(self, values: &[usize]) -> usize {
+        match self {
+            Aggregation::Sum => values.iter().sum(),
+            Aggregation::Avg if values.is_empty() => 0,
+            Aggregation::Avg => values.iter().sum::<usize>() / values.len(),
+            Aggregation::Max => values.iter().copied().max().unwrap_or(0),
+        }
+    }
+}
+
+/// Which end of a metric's scale needs attention first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// Higher values are worse, like lines or complexity.
+    HigherIsWorse,
+    /// Lower values are worse, like the bus factor.
+    LowerIsWorse,
+}
+
+impl Direction {
+    /// Orders worse values first.
+    pub fn worst_first(self, a: usize, b: usize) -> std::cmp::Ordering {
+        match self {
+            Direction::HigherIsWorse => b.cmp(&a),
+            Direction::LowerIsWorse => a.cmp(&b),
+        }
+    }
+
+    /// Whether going from `before` to `after` is a change for the worse.
+    pub fn worsened(self, before: usize, after: usize) -> bool {
+        self.worst_first(after, before).is_lt()
+    }
+
+    /// Whether `value` is within `percent`% of `worst`, the worst value seen.
+    pub fn within(self, value: usize, worst: usize, percent: usize) -> bool {
+        match self {
+            Direction::HigherIsWorse => value * 100 >= worst * percent,
+            Direction::LowerIsWorse => value * percent <= worst * 100,
+        }
+    }
+}
+
+/// Everything a metric may need to score one file. `source` is `None` when no
+/// selected metric needs file contents or the file couldn't be read.
+pub struct MetricContext<'a> {
+    pub path: &'a Path,
+    pub source: Option<&'a SourceFile>,
+    /// The file's chunk hashes when a metric needs the duplication index.
+    pub chunks: Option<&'a FileChunks>,
+    pub duplication: Option<&'a DuplicationIndex>,
+    /// The repository's history when a metric needs it and the path is in a repo.
+    pub history: Option<&'a GitHistory>,
+    pub days: u32,
+    /// How `hotspots` scores the file.
+    pub score: &'a ScoreFormula,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct MetricOutput {
+    pub value: usize,
+    /// Short human-readable form shown after the path in text output (e.g. `12%`).
+    pub info: String,
+    pub extras: Extras,
+}
+
+impl MetricOutput {
+    fn value(value: usize) -> Self {
+        MetricOutput {
+            value,
+            ..Default::default()
+        }
+    }
+
+    fn with_info(value: usize, info: String) -> Self {
+        MetricOutput {
+            value,
+            info,
+            extras: Extras::new(),
+        }
+    }
+}
+
+pub trait Metric: Sync {
+    fn name(&self) -> &'static str;
+    fn unit(&self) -> &'static str;
+    /// Label printed next to the totals row.
+    fn label(&self) -> &'static str;
+    /// Reference value for color scaling when `--max-lines` isn't given.
+    fn default_max(&self) -> usize;
+    fn compute(&self, ctx: &MetricContext) -> MetricOutput;
+
+    fn aggregation(&self) -> Aggregation {
+        Aggregation::Sum
+    }
+
+    /// Which values are ranked first and count as regressions against a baseline.
+    fn direction(&self) -> Direction {
+        Direction::HigherIsWorse
+    }
+
+    fn needs_content(&self) -> bool {
+        true
+    }
+
+    /// Whether the text output appends `MetricOutput::info` after the path.
+    fn shows_extra_info(&self) -> bool {
+        false
+    }
+
+    /// Keys of `MetricOutput::extras` that get their own CSV/TSV column.
+    fn extra_columns(&self, _dirs: bool) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Info and extras for a value rolled up over several files (e.g. a directory).
+    fn aggregate_output(&self, value: usize) -> MetricOutput {
+        MetricOutput::value(value)
+    }
+
+    /// Rolls several files' extras into one output (e.g. for a directory), for
+    /// metrics whose aggregate can't be derived from the per-file values alone.
+    /// `None` falls back to `aggregation` and `aggregate_output`.
+    fn combine(&self, _files: &[&Extras]) -> Option<MetricOutput> {
+        None
+    }
+
+    /// Whether `MetricContext::duplication` must be built before computing.
+    fn needs_duplication_index(&self) -> bool {
+        false
+    }
+
+    /// Whether `MetricContext::history` must be loaded before computing.
+    fn needs_history(&self) -> bool {
+        false
+    }
+
+    /// Whether the output depends on nothing but the file's contents, so it can be
+    /// reused from the cache while the file is unchanged.
+    fn cacheable(&self) -> bool {
+        self.needs_content() && !self.needs_duplication_index() && !self.needs_history()
+    }
+}
+
+pub struct Size;
+pub struct Chars;
+pub struct Indent;
+pub struct Isolation;
+pub struct Rhythm;
+pub struct Ownership;
+pub struct AuthorCount;
+pub struct LineOwnership;
+pub struct BusFactor;
+pub struct LineBusFactor;
+pub struct Age;
+pub struct Duplicates;
+pub struct Emoji;
+pub struct Density;
+pub struct Hotspots;
+pub struct Churn;
+/// Churn in changed lines rather than commits; `Commits` isn't used.
+pub struct LineChurn(pub ChurnKind);
+pub struct Complexity;
+/// A statistic over the complexity of a file's functions.
+pub struct FunctionComplexity(pub FunctionStat);
+/// Lines with code, as in the `--loc` breakdown.
+pub struct Code;
+pub struct Comment;
+pub struct Blank;
+/// Lines with both code and a comment, also counted by `Code`.
+pub struct Mixed;
+pub struct Lines;
+
+/// Every known metric. When several are selected they're shown in this order.
+pub static REGISTRY: &[&dyn Metric] = &[
+    &Size,
+    &Chars,
+    &Indent,
+    &Isolation,
+    &Rhythm,
+    &Ownership,
+    &AuthorCount,
+    &LineOwnership,
+    &BusFactor,
+    &LineBusFactor,
+    &Age,
+    &Duplicates,
+    &Emoji,
+    &Density,
+    &Hotspots,
+    &Churn,
+    &LineChurn(ChurnKind::Added),
+    &LineChurn(ChurnKind::Deleted),
+    &LineChurn(ChurnKind::Changed),
+    &LineChurn(ChurnKind::Relative),
+    &Complexity,
+    &FunctionComplexity(FunctionStat::Max),
+    &FunctionComplexity(FunctionStat::Mean),
+    &FunctionComplexity(FunctionStat::P90),
+    &Code,
+    &Comment,
+    &Blank,
+    &Mixed,
+    &Lines,
+];
+
+pub fn find_metric(name: &str) -> Option<&'static dyn Metric> {
+    REGISTRY
+        .iter()
+        .copied()
+        .find(|metric| metric.name() == name)
+}
+
+pub(crate) fn registry_position(name: &str) -> usize {
+    REGISTRY
+        .iter()
+        .position(|metric| metric.name() == name)
+        .unwrap_or(REGISTRY.len())
+}
+
+pub fn metric_names() -> Vec<&'static str> {
+    REGISTRY.iter().map(|metric| metric.name()).collect()
+}
+
+impl Metric for Size {
+    fn name(&self) -> &'static str {
+        "size"
+    }
+    fn unit(&self) -> &'static str {
+        "bytes"
+    }
+    fn label(&self) -> &'static str {
+        "total bytes"
+    }
+    fn default_max(&self) -> usize {
+        1000000
+    }
+    fn needs_content(&self) -> bool {
+        false
+    }
+    fn shows_extra_info(&self) -> bool {
+        true
+    }
+    fn extra_columns(&self, _dirs: bool) -> &'static [&'static str] {
+        &["human"]
+    }
+    fn compute(&self, ctx: &MetricContext) -> MetricOutput {
+        let size = match ctx.source {
+            Some(source) => source.size,
+            None => get_file_size(ctx.path).unwrap_or(0),
+        };
+        self.aggregate_output(size)
+    }
+    fn aggregate_output(&self, value: usize) -> MetricOutput {
+        let mut output = MetricOutput::with_info(value, format_size(value));
+        output
+            .extras
+            .insert("human".to_string(), format_size(value).into());
+        output
+    }
+}
+
+impl Metric for Chars {
+    fn name(&self) -> &'static str {
+        "chars"
+    }
+    fn unit(&self) -> &'static str {
+        "chars"
+    }
+    fn label(&self) -> &'static str {
+        "total chars"
+    }
+    fn default_max(&self) -> usize {
+        50000
+    }
+    fn compute(&self, ctx: &MetricContext) -> MetricOutput {
+        MetricOutput::value(ctx.source.map_or(0, count_nonwhitespace_chars))
+    }
+}
+
+impl Metric for Indent {
+    fn name(&self) -> &'static str {
+        "indent"
+    }
+    fn unit(&self) -> &'static str {
+        "levels"
+    }
+    fn label(&self) -> &'static str {
+        "max indent depth"
+    }
+    fn default_max(&self) -> usize {
+        20
+    }
+    fn aggregation(&self) -> Aggregation {
+        Aggregation::Max
+    }
+    fn shows_extra_info(&self) -> bool {
+        true
+    }
+    fn compute(&self, ctx: &MetricContext) -> MetricOutput {
+        let max_indent = ctx.source.map_or(0, calculate_max_indent_level);
+        MetricOutput::with_info(max_indent, format!("{max_indent}↓"))
+    }
+}
+
+impl Metric for Isolation {
+    fn name(&self) -> &'static str {
+        "isolation"
+    }
+    fn unit(&self) -> &'static str {
+        "percent"
+    }
+    fn label(&self) -> &'static str {
+        "avg isolation %"
+    }
+    fn default_max(&self) -> usize {
+        100
+    }
+    fn aggregation(&self) -> Aggregation {
+        Aggregation::Avg
+    }
+    // Files mostly changed alongside others are the tangled ones
+    fn direction(&self) -> Direction {
+        Direction::LowerIsWorse
+    }
+    fn needs_content(&self) -> bool {
+        false
+    }
+    fn needs_history(&self) -> bool {
+        true
+    }
+    fn shows_extra_info(&self) -> bool {
+        true
+    }
+    fn compute(&self, ctx: &MetricContext) -> MetricOutput {
+        let isolation_pct = ctx.history.map_or(0, |history| {
+            calculate_isolation_percentage(history, ctx.path)
+        });
+        MetricOutput::with_info(isolation_pct, format!("{isolation_pct}%"))
+    }
+}
+
+impl Metric for Rhythm {
+    fn name(&self) -> &'static str {
+        "rhythm"
+    }
+    fn unit(&self) -> &'static str {
+        "days"
+    }
+    fn label(&self) -> &'static str {
+        "avg rhythm score"
+    }
+    fn default_max(&self) -> usize {
+        50
+    }
+    fn aggregation(&self) -> Aggregation {
+        Aggregation::Avg
+    }
+    fn needs_content(&self) -> bool {
+        false
+    }
+    fn needs_history(&self) -> bool {
+        true
+    }
+    fn shows_extra_info(&self) -> bool {
+        true
+    }
+    fn compute(&self, ctx: &MetricContext) -> MetricOutput {
+        let rhythm_score = ctx
+            .history
+            .map_or(0, |history| calculate_rhythm_score(history, ctx.path));
+        MetricOutput::with_info(rhythm_score, format!("{rhythm_score}d"))
+    }
+}
+
+impl Metric for Ownership {
+    fn name(&self) -> &'static str {
+        "ownership"
+    }
+    fn unit(&self) -> &'static str {
+        "percent"
+    }
+    fn label(&self) -> &'static str {
+        "avg ownership %"
+    }
+    fn default_max(&self) -> usize {
+        100
+    }
+    fn aggregation(&self) -> Aggregation {
+        Aggregation::Avg
+    }
+    fn needs_content(&self) -> bool {
+        false
+    }
+    fn needs_history(&self) -> bool {
+        true
+    }
+    fn shows_extra_info(&self) -> bool {
+        true
+    }
+    fn compute(&self, ctx: &MetricContext) -> MetricOutput {
+        let owner_pct = ctx.history.map_or(0, |history| {
+            calculate_ownership_percentage(history, ctx.path)
+        });
+        MetricOutput::with_info(owner_pct, format!("{owner_pct}%"))
+    }
+}
+
+impl Metric for AuthorCount {
+    fn name(&self) -> &'static str {
+        "author-count"
+    }
+    fn unit(&self) -> &'static str {
+        "authors"
+    }
+    fn label(&self) -> &'static str {
+        "avg authors"
+    }
+    fn default_max(&self) -> usize {
+        10
+    }
+    fn aggregation(&self) -> Aggregation {
+        Aggregation::Avg
+    }
+    fn needs_content(&self) -> bool {
+        false
+    }
+    fn needs_history(&self) -> bool {
+        true
+    }
+    fn compute(&self, ctx: &MetricContext) -> MetricOutput {
+        MetricOutput::value(
+            ctx.history
+                .map_or(0, |history| author_counts(history, ctx.path).len()),
+        )
+    }
+}
+
+impl LineOwnership {
+    /// Top owner's share of `lines` (author → surviving lines), with the owner and
+    /// author count as extras.
+    fn from_lines(lines: &BTreeMap<String, usize>) -> MetricOutput {
+        let total: usize = lines.values().sum();
+        let Some((owner, owned)) = lines
+            .iter()
+            .max_by(|(a, a_lines), (b, b_lines)| a_lines.cmp(b_lines).then(b.cmp(a)))
+        else {
+            return MetricOutput::default();
+        };
+
+        let percent = owned * 100 / total;
+        let mut output = MetricOutput::with_info(
+            percent,
+            format!("{owner} {percent}%, {} authors", lines.len()),
+        );
+        output
+            .extras
+            .insert("owner".to_string(), owner.clone().into());
+        output
+            .extras
+            .insert("authors".to_string(), lines.len().into());
+        output.extras.insert(
+            "lines".to_string(),
+            serde_json::to_value(lines).unwrap_or_default(),
+        );
+        output
+    }
+}
+
+impl Metric for LineOwnership {
+    fn name(&self) -> &'static str {
+        "line-ownership"
+    }
+    fn unit(&self) -> &'static str {
+        "percent"
+    }
+    fn label(&self) -> &'static str {
+        "avg line ownership %"
+    }
+    fn default_max(&self) -> usize {
+        100
+    }
+    fn aggregation(&self) -> Aggregation {
+        Aggregation::Avg
+    }
+    fn needs_content(&self) -> bool {
+        false
+    }
+    fn needs_history(&self) -> bool {
+        true
+    }
+    fn shows_extra_info(&self) -> bool {
+        true
+    }
+    fn extra_columns(&self, _dirs: bool) -> &'static [&'static str] {
+        &["owner", "authors"]
+    }
+    fn combine(&self, files: &[&Extras]) -> Option<MetricOutput> {
+        Some(Self::from_lines(&sum_author_counts(files, "lines")))
+    }
+    fn compute(&self, ctx: &MetricContext) -> MetricOutput {
+        ctx.history.map_or_else(MetricOutput::default, |history| {
+            Self::from_lines(&history.blame_lines(ctx.path))
+        })
+    }
+}
+
+/// Adds up the per-author counts each file keeps under `key` in its extras.
+fn sum_author_counts(files: &[&Extras], key: &str) -> BTreeMap<String, usize> {
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for extras in files {
+        let Some(serde_json::Value::Object(file_counts)) = extras.get(key) else {
+            continue;
+        };
+        for (author, count) in file_counts {
+            *counts.entry(author.clone()).or_insert(0) += count.as_u64().unwrap_or(0) as usize;
+        }
+    }
+    counts
+}
+
+/// Bus factor of `counts` (author → commits or lines), naming the authors it
+/// depends on. A bus factor of 1 is flagged in the info.
+fn bus_factor_output(counts: &BTreeMap<String, usize>, unit: &str) -> MetricOutput {
+    let total: usize = counts.values().sum();
+    let authors = bus_factor_authors(counts);
+    let shares: Vec<String> = authors
+        .iter()
+        .map(|(author, count)| format!("{author} {}%", count * 100 / total))
+        .collect();
+    let info = match shares.as_slice() {
+        [] => String::new(),
+        [share] => format!("at risk: {share} of {unit}"),
+        _ => shares.join(", "),
+    };
+
+    let mut output = MetricOutput::with_info(authors.len(), info);
+    let depends_on: Vec<&str> = authors.iter().map(|(author, _)| *author).collect();
+    output
+        .extras
+        .insert("depends_on".to_string(), depends_on.join(",").into());
+    output.extras.insert(
+        "counts".to_string(),
+        serde_json::to_value(counts).unwrap_or_default(),
+    );
+    output
+}
+
+impl Metric for BusFactor {
+    fn name(&self) -> &'static str {
+        "bus-factor"
+    }
+    fn unit(&self) -> &'static str {
+        "authors"
+    }
+    fn label(&self) -> &'static str {
+        "avg bus factor"
+    }
+    fn default_max(&self) -> usize {
+        5
+    }
+    fn aggregation(&self) -> Aggregation {
+        Aggregation::Avg
+    }
+    // Few authors is the risk
+    fn direction(&self) -> Direction {
+        Direction::LowerIsWorse
+    }
+    fn needs_content(&self) -> bool {
+        false
+    }
+    fn needs_history(&self) -> bool {
+        true
+    }
+    fn shows_extra_info(&self) -> bool {
+        true
+    }
+    fn extra_columns(&self, _dirs: bool) -> &'static [&'static str] {
+        &["depends_on"]
+    }
+    fn combine(&self, files: &[&Extras]) -> Option<MetricOutput> {
+        Some(bus_factor_output(
+            &sum_author_counts(files, "counts"),
+            "commits",
+        ))
+    }
+    fn compute(&self, ctx: &MetricContext) -> MetricOutput {
+        let counts = ctx.history.map_or_else(BTreeMap::new, |history| {
+            author_counts(history, ctx.path).into_iter().collect()
+        });
+        bus_factor_output(&counts, "commits")
+    }
+}
+
+impl Metric for LineBusFactor {
+    fn name(&self) -> &'static str {
+        "line-bus-factor"
+    }
+    fn unit(&self) -> &'static str {
+        "authors"
+    }
+    fn label(&self) -> &'static str {
+        "avg bus factor (lines)"
+    }
+    fn default_max(&self) -> usize {
+        5
+    }
+    fn aggregation(&self) -> Aggregation {
+        Aggregation::Avg
+    }
+    // Like `BusFactor`
+    fn direction(&self) -> Direction {
+        Direction::LowerIsWorse
+    }
+    fn needs_content(&self) -> bool {
+        false
+    }
+    fn needs_history(&self) -> bool {
+        true
+    }
+    fn shows_extra_info(&self) -> bool {
+        true
+    }
+    fn extra_columns(&self, _dirs: bool) -> &'static [&'static str] {
+        &["depends_on"]
+    }
+    fn combine(&self, files: &[&Extras]) -> Option<MetricOutput> {
+        Some(bus_factor_output(
+            &sum_author_counts(files, "counts"),
+            "lines",
+        ))
+    }
+    fn compute(&self, ctx: &MetricContext) -> MetricOutput {
+        let counts = ctx
+            .history
+            .map_or_else(BTreeMap::new, |history| history.blame_lines(ctx.path));
+        bus_factor_output(&counts, "lines")
+    }
+}
+
+impl Metric for Age {
+    fn name(&self) -> &'static str {
+        "age"
+    }
+    fn unit(&self) -> &'static str {
+        "days"
+    }
+    fn label(&self) -> &'static str {
+        "avg age (days)"
+    }
+    fn default_max(&self) -> usize {
+        365
+    }
+    fn aggregation(&self) -> Aggregation {
+        Aggregation::Avg
+    }
+    fn needs_content(&self) -> bool {
+        false
+    }
+    fn needs_history(&self) -> bool {
+        true
+    }
+    fn shows_extra_info(&self) -> bool {
+        true
+    }
+    fn compute(&self, ctx: &MetricContext) -> MetricOutput {
+        let days_old = calculate_file_age_days(ctx.history, ctx.path).unwrap_or(0);
+        MetricOutput::with_info(days_old, format!("{days_old}d"))
+    }
+}
+
+impl Metric for Duplicates {
+    fn name(&self) -> &'static str {
+        "duplicates"
+    }
+    fn unit(&self) -> &'static str {
+        "percent"
+    }
+    fn label(&self) -> &'static str {
+        "avg duplication %"
+    }
+    fn default_max(&self) -> usize {
+        50
+    }
+    fn aggregation(&self) -> Aggregation {
+        Aggregation::Avg
+    }
+    fn shows_extra_info(&self) -> bool {
+        true
+    }
+    fn needs_duplication_index(&self) -> bool {
+        true
+    }
+    fn compute(&self, ctx: &MetricContext) -> MetricOutput {
+        let dup_pct = match (ctx.chunks, ctx.duplication) {
+            (Some(chunks), Some(index)) => index.duplication_percentage(chunks),
+            _ => 0,
+        };
+        MetricOutput::with_info(dup_pct, format!("{dup_pct}%"))
+    }
+}
+
+impl Metric for Emoji {
+    fn name(&self) -> &'static str {
+        "emoji"
+    }
+    fn unit(&self) -> &'static str {
+        "emojis"
+    }
+    fn label(&self) -> &'static str {
+        "total emojis"
+    }
+    fn default_max(&self) -> usize {
+        10
+    }
+    fn shows_extra_info(&self) -> bool {
+        true
+    }
+    fn extra_columns(&self, dirs: bool) -> &'static [&'static str] {
+        if dirs {
+            &[]
+        } else {
+            &["unique", "most_common"]
+        }
+    }
+    fn compute(&self, ctx: &MetricContext) -> MetricOutput {
+        let info = ctx.source.map(analyze_emojis).unwrap_or_default();
+        let mut output =
+            MetricOutput::with_info(info.total, format!("{}u {}", info.unique, info.most_common));
+        output
+            .extras
+            .insert("unique".to_string(), info.unique.into());
+        output
+            .extras
+            .insert("most_common".to_string(), info.most_common.into());
+        output
+    }
+}
+
+impl Metric for Density {
+    fn name(&self) -> &'static str {
+        "density"
+    }
+    fn unit(&self) -> &'static str {
+        "score"
+    }
+    fn label(&self) -> &'static str {
+        "total density score"
+    }
+    fn default_max(&self) -> usize {
+        80
+    }
+    fn compute(&self, ctx: &MetricContext) -> MetricOutput {
+        MetricOutput::value(ctx.source.map_or(0, calculate_code_density))
+    }
+}
+
+impl Metric for Hotspots {
+    fn name(&self) -> &'static str {
+        "hotspots"
+    }
+    fn unit(&self) -> &'static str {
+        "score"
+    }
+    fn label(&self) -> &'static str {
+        "total hotspot score"
+    }
+    fn default_max(&self) -> usize {
+        200
+    }
+    fn needs_history(&self) -> bool {
+        true
+    }
+    fn shows_extra_info(&self) -> bool {
+        true
+    }
+    fn extra_columns(&self, _dirs: bool) -> &'static [&'static str] {
+        &["factors"]
+    }
+    fn compute(&self, ctx: &MetricContext) -> MetricOutput {
+        let factors: BTreeMap<String, usize> = ctx
+            .score
+            .metrics()
+            .iter()
+            .map(|metric| (metric.name().to_string(), metric.compute(ctx).value))
+            .collect();
+        Hotspots::scored(ctx.score, &factors, &BTreeMap::new())
+    }
+}
+
+impl Hotspots {
+    /// Output for a file whose score factors have the values `factors`, keeping
+    /// them in the extras so the score can be recomputed once `maxima` are known.
+    pub(crate) fn scored(
+        score: &ScoreFormula,
+        factors: &BTreeMap<String, usize>,
+        maxima: &BTreeMap<String, usize>,
+    ) -> MetricOutput {
+        let mut output =
+            MetricOutput::with_info(score.evaluate(factors, maxima), score.explain(factors));
+        output.extras.insert(
+            "factors".to_string(),
+            serde_json::to_value(factors).unwrap_or_default(),
+        );
+        output
+    }
+
+    /// The factor values `scored` kept in a file's extras.
+    pub(crate) fn factors(extras: &Extras) -> BTreeMap<String, usize> {
+        extras
+            .get("factors")
+            .and_then(|factors| serde_json::from_value(factors.clone()).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// `path`'s churn measured as `kind`.
+fn churn(ctx: &MetricContext, kind: ChurnKind) -> usize {
+    let Some(history) = ctx.history else {
+        return 0;
+    };
+    let changed = || {
+        let (added, deleted) = calculate_line_churn(history, ctx.path, ctx.days);
+        added + deleted
+    };
+    match kind {
+        ChurnKind::Commits => calculate_churn(history, ctx.path, ctx.days),
+        ChurnKind::Added => calculate_line_churn(history, ctx.path, ctx.days).0,
+        ChurnKind::Deleted => calculate_line_churn(history, ctx.path, ctx.days).1,
+        ChurnKind::Changed => changed(),
+        ChurnKind::Relative => changed() * 100 / ctx.source.map_or(0, count_lines).max(1),
+    }
+}
+
+impl Metric for Churn {
+    fn name(&self) -> &'static str {
+        "churn"
+    }
+    fn unit(&self) -> &'static str {
+        "commits"
+    }
+    fn label(&self) -> &'static str {
+        "total changes"
+    }
+    fn default_max(&self) -> usize {
+        50
+    }
+    fn needs_content(&self) -> bool {
+        false
+    }
+    fn needs_history(&self) -> bool {
+        true
+    }
+    fn compute(&self, ctx: &MetricContext) -> MetricOutput {
+        MetricOutput::value(churn(ctx, ChurnKind::Commits))
+    }
+}
+
+impl Metric for LineChurn {
+    fn name(&self) -> &'static str {
+        self.0.metric_name()
+    }
+    fn unit(&self) -> &'static str {
+        match self.0 {
+            ChurnKind::Relative => "percent",
+            _ => "lines",
+        }
+    }
+    fn label(&self) -> &'static str {
+        match self.0 {
+            ChurnKind::Added => "total lines added",
+            ChurnKind::Deleted => "total lines deleted",
+            ChurnKind::Relative => "avg relative churn %",
+            _ => "total lines changed",
+        }
+    }
+    fn default_max(&self) -> usize {
+        match self.0 {
+            ChurnKind::Relative => 300,
+            _ => 2000,
+        }
+    }
+    fn aggregation(&self) -> Aggregation {
+        match self.0 {
+            ChurnKind::Relative => Aggregation::Avg,
+            _ => Aggregation::Sum,
+        }
+    }
+    fn needs_content(&self) -> bool {
+        // Relative churn divides by the current line count
+        self.0 == ChurnKind::Relative
+    }
+    fn needs_history(&self) -> bool {
+        true
+    }
+    fn compute(&self, ctx: &MetricContext) -> MetricOutput {
+        MetricOutput::value(churn(ctx, self.0))
+    }
+}
+
+impl Metric for Complexity {
+    fn name(&self) -> &'static str {
+        "complexity"
+    }
+    fn unit(&self) -> &'static str {
+        "score"
+    }
+    fn label(&self) -> &'static str {
+        "total complexity"
+    }
+    fn default_max(&self) -> usize {
+        20
+    }
+    fn compute(&self, ctx: &MetricContext) -> MetricOutput {
+        MetricOutput::value(ctx.source.map_or(0, calculate_complexity))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FunctionStat {
+    Max,
+    Mean,
+    P90,
+}
+
+impl Metric for FunctionComplexity {
+    fn name(&self) -> &'static str {
+        match self.0 {
+            FunctionStat::Max => "function-max",
+            FunctionStat::Mean => "function-mean",
+            FunctionStat::P90 => "function-p90",
+        }
+    }
+    fn unit(&self) -> &'static str {
+        "score"
+    }
+    fn label(&self) -> &'static str {
+        match self.0 {
+            FunctionStat::Max => "most complex function",
+            FunctionStat::Mean => "average function complexity",
+            FunctionStat::P90 => "highest p90 function complexity",
+        }
+    }
+    fn default_max(&self) -> usize {
+        match self.0 {
+            FunctionStat::Max | FunctionStat::P90 => 15,
+            FunctionStat::Mean => 8,
+        }
+    }
+    fn aggregation(&self) -> Aggregation {
+        match self.0 {
+            FunctionStat::Mean => Aggregation::Avg,
+            FunctionStat::Max | FunctionStat::P90 => Aggregation::Max,
+        }
+    }
+    fn shows_extra_info(&self) -> bool {
+        self.0 == FunctionStat::Max
+    }
+    fn compute(&self, ctx: &MetricContext) -> MetricOutput {
+        let Some(source) = ctx.source else {
+            return MetricOutput::default();
+        };
+        let functions = source.functions();
+        let (max, mean, p90) = complexity_stats(functions);
+        match self.0 {
+            // Name the function to look at first
+            FunctionStat::Max => {
+                let info = functions
+                    .iter()
+                    .filter(|function| function.complexity == max)
+                    .map(|function| format!("{}:{}", function.name, function.line))
+                    .next()
+                    .unwrap_or_default();
+                MetricOutput::with_info(max, info)
+            }
+            FunctionStat::Mean => MetricOutput::value(mean),
+            FunctionStat::P90 => MetricOutput::value(p90),
+        }
+    }
+}
+
+impl Metric for Code {
+    fn name(&self) -> &'static str {
+        "code"
+    }
+    fn unit(&self) -> &'static str {
+        "lines"
+    }
+    fn label(&self) -> &'static str {
+        "total code lines"
+    }
+    fn default_max(&self) -> usize {
+        800
+    }
+    fn shows_extra_info(&self) -> bool {
+        true
+    }
+    fn extra_columns(&self, dirs: bool) -> &'static [&'static str] {
+        if dirs { &[] } else { &["language"] }
+    }
+    fn compute(&self, ctx: &MetricContext) -> MetricOutput {
+        let Some(source) = ctx.source else {
+            return MetricOutput::default();
+        };
+        let language = source
+            .language()
+            .map_or(OTHER_LANGUAGE, |language| language.name);
+        let mut output = MetricOutput::with_info(source.line_counts().code, language.to_string());
+        output
+            .extras
+            .insert("language".to_string(), language.into());
+        output
+    }
+}
+
+/// What `Code` reports as the language of files no language rules match.
+pub const OTHER_LANGUAGE: &str = "Other";
+
+impl Metric for Comment {
+    fn name(&self) -> &'static str {
+        "comment"
+    }
+    fn unit(&self) -> &'static str {
+        "lines"
+    }
+    fn label(&self) -> &'static str {
+        "total comment lines"
+    }
+    fn default_max(&self) -> usize {
+        200
+    }
+    fn compute(&self, ctx: &MetricContext) -> MetricOutput {
+        MetricOutput::value(ctx.source.map_or(0, |source| source.line_counts().comment))
+    }
+}
+
+impl Metric for Blank {
+    fn name(&self) -> &'static str {
+        "blank"
+    }
+    fn unit(&self) -> &'static str {
+        "lines"
+    }
+    fn label(&self) -> &'static str {
+        "total blank lines"
+    }
+    fn default_max(&self) -> usize {
+        200
+    }
+    fn compute(&self, ctx: &MetricContext) -> MetricOutput {
+        MetricOutput::value(ctx.source.map_or(0, |source| source.line_counts().blank))
+    }
+}
+
+impl Metric for Mixed {
+    fn name(&self) -> &'static str {
+        "mixed"
+    }
+    fn unit(&self) -> &'static str {
+        "lines"
+    }
+    fn label(&self) -> &'static str {
+        "total mixed lines"
+    }
+    fn default_max(&self) -> usize {
+        100
+    }
+    fn compute(&self, ctx: &MetricContext) -> MetricOutput {
+        MetricOutput::value(ctx.source.map_or(0, |source| source.line_counts().mixed))
+    }
+}
+
+impl Metric for Lines {
+    fn name(&self) -> &'static str {
+        "lines"
+    }
+    fn unit(&self) -> &'static str {
+        "lines"
+    }
+    fn label(&self) -> &'static str {
+        "total"
+    }
+    fn default_max(&self) -> usize {
+        1000
+    }
+    fn compute(&self, ctx: &MetricContext) -> MetricOutput {
+        MetricOutput::value(ctx.source.map_or(0, count_lines))
+    }
+}
@@ -0,0 +1,180 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Per-metric values that don't fit the single numeric column (e.g. emoji stats).
+pub type Extras = serde_json::Map<String, serde_json::Value>;
+
+pub type MetricValues = BTreeMap<&'static str, MetricValue>;
+
+#[derive(Serialize)]
+pub struct MetricValue {
+    pub unit: &'static str,
+    pub value: usize,
+    #[serde(flatten)]
+    pub extras: Extras,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Record {
+    File {
+        path: PathBuf,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        author: Option<String>,
+        /// Earlier names in git history, most recent first.
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        former_paths: Vec<String>,
+        metrics: MetricValues,
+    },
+    Dir {
+        path: PathBuf,
+        files: usize,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        authors: Vec<String>,
+        metrics: MetricValues,
+    },
+    Extension {
+        extension: String,
+        files: usize,
+        metrics: MetricValues,
+    },
+    Language {
+        language: String,
+        files: usize,
+        metrics: MetricValues,
+    },
+    Total {
+        count: usize,
+        label: &'static str,
+        metrics: MetricValues,
+    },
+}
+
+pub fn write_json<T: Serialize>(records: &[T]) {
+    println!("{}", serde_json::to_string_pretty(records).unwrap());
+}
+
+pub fn write_ndjson<T: Serialize>(records: &[T]) {
+    for record in records {
+        println!("{}", serde_json::to_string(record).unwrap());
+    }
+}
+
+/// A column of a CSV/TSV report table.
+#[derive(Clone, Copy)]
+pub enum Column {
+    /// The path, extension or language naming the row, headed by its kind.
+    Key(&'static str),
+    Files,
+    /// `author` for files, `authors` for directories.
+    Author(&'static str),
+    FormerPaths,
+    Metric(&'static str),
+    /// A key of a metric's extras, headed `<metric>_<key>`.
+    Extra(&'static str, &'static str),
+}
+
+impl Column {
+    pub fn name(&self) -> String {
+        match self {
+            Column::Key(name) | Column::Author(name) | Column::Metric(name) => name.to_string(),
+            Column::Files => "files".to_string(),
+            Column::FormerPaths => "former_paths".to_string(),
+            Column::Extra(metric, key) => format!("{metric}_{key}"),
+        }
+    }
+}
+
+/// Writes records as a CSV (`,`) or TSV (`\t`) table. Columns are fixed for a given
+/// set of metrics and flags so the header doesn't change with the data; totals are left out
+/// since they're derivable.
+pub fn write_delimited(records: &[Record], delimiter: char, header: &[Column]) {
+    print_row(header.iter().map(Column::name), delimiter);
+    for record in records {
+        if matches!(record, Record::Total { .. }) {
+            continue;
+        }
+        print_row(header.iter().map(|column| cell(record, *column)), delimiter);
+    }
+}
+
+fn cell(record: &Record, column: Column) -> String {
+    let (key, files, author, metrics) = match record {
+        Record::File { former_paths, .. } if matches!(column, Column::FormerPaths) => {
+            return former_paths.join(";");
+        }
+        Record::File {
+            path,
+            author,
+            metrics,
+            ..
+        } => (
+            path.to_string_lossy().into_owned(),
+            None,
+            author.clone().unwrap_or_default(),
+            metrics,
+        ),
+        Record::Dir {
+            path,
+            files,
+            authors,
+            metrics,
+        } => (
+            path.to_string_lossy().into_owned(),
+            Some(*files),
+            authors.join(";"),
+            metrics,
+        ),
+        Record::Extension {
+            extension,
+            files,
+            metrics,
+        } => (extension.clone(), Some(*files), String::new(), metrics),
+        Record::Language {
+            language,
+            files,
+            metrics,
+        } => (language.clone(), Some(*files), String::new(), metrics),
+        Record::Total { .. } => return String::new(),
+    };
+
+    match column {
+        Column::Key(_) => key,
+        Column::Files => files.map(|files| files.to_string()).unwrap_or_default(),
+        Column::Author(_) => author,
+        Column::FormerPaths => String::new(),
+        Column::Metric(name) => metrics
+            .get(name)
+            .map(|metric| metric.value.to_string())
+            .unwrap_or_default(),
+        Column::Extra(name, key) => {
+            match metrics.get(name).and_then(|metric| metric.extras.get(key)) {
+                Some(serde_json::Value::String(text)) => text.clone(),
+                Some(value) => value.to_string(),
+                None => String::new(),
+            }
+        }
+    }
+}
+
+/// Writes a header and rows of already formatted cells as CSV or TSV.
+pub fn write_table(header: &[&str], rows: impl Iterator<Item = Vec<String>>, delimiter: char) {
+    print_row(header.iter().map(|column| column.to_string()), delimiter);
+    for row in rows {
+        print_row(row.into_iter(), delimiter);
+    }
+}
+
+fn print_row(cells: impl Iterator<Item = String>, delimiter: char) {
+    let line: Vec<String> = cells.map(|cell| quote_cell(&cell, delimiter)).collect();
+    println!("{}", line.join(&delimiter.to_string()));
+}
+
+fn quote_cell(cell: &str, delimiter: char) -> String {
+    if cell.contains([delimiter, '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
@@ -0,0 +1,158 @@
+use crate::metrics::{Metric, find_metric, metric_names};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+/// How `hotspots` scores a file: a sum of products of numbers and metric values,
+/// such as `complexity * churn-lines` or
+/// `0.5*norm(complexity) + 0.3*norm(churn) + 0.2*norm(author-count)`.
+/// `norm(metric)` scales a metric to 0–100 relative to the highest value among
+/// the analyzed files, so metrics of different magnitudes can be weighed.
+#[derive(Clone)]
+pub struct ScoreFormula {
+    pub expression: String,
+    /// Terms to add up, each a product of factors.
+    terms: Vec<Vec<Factor>>,
+}
+
+#[derive(Clone)]
+enum Factor {
+    Number(f64),
+    Metric(&'static dyn Metric),
+    Normalized(&'static dyn Metric),
+}
+
+impl FromStr for ScoreFormula {
+    type Err = String;
+
+    fn from_str(expression: &str) -> Result<Self, Self::Err> {
+        let trimmed: String = expression.chars().filter(|c| !c.is_whitespace()).collect();
+        let error = |reason: String| format!("invalid score '{expression}': {reason}");
+
+        let mut terms = Vec::new();
+        for term in trimmed.split('+') {
+            let mut factors = Vec::new();
+            for factor in term.split('*') {
+                if factor.is_empty() {
+                    return Err(error(
+                        "expected metrics and numbers joined by * and +, e.g. complexity*churn"
+                            .to_string(),
+                    ));
+                }
+                if let Ok(number) = factor.parse::<f64>() {
+                    factors.push(Factor::Number(number));
+                    continue;
+                }
+
+                let (name, normalized) = match factor
+                    .strip_prefix("norm(")
+                    .and_then(|rest| rest.strip_suffix(')'))
+                {
+                    Some(name) => (name, true),
+                    None => (factor, false),
+                };
+                let metric = find_metric(name).ok_or_else(|| {
+                    error(format!(
+                        "unknown metric '{name}' (expected one of {})",
+                        metric_names().join(", ")
+                    ))
+                })?;
+                if metric.name() == "hotspots" {
+                    return Err(error("hotspots can't be part of its own score".to_string()));
+                }
+                factors.push(if normalized {
+                    Factor::Normalized(metric)
+                } else {
+                    Factor::Metric(metric)
+                });
+            }
+            terms.push(factors);
+        }
+
+        Ok(ScoreFormula {
+            expression: expression.trim().to_string(),
+            terms,
+        })
+    }
+}
+
+impl ScoreFormula {
+    /// The score multiplying the values of `metrics`, e.g. the default
+    /// `complexity * churn`.
+    pub fn product(metrics: &[&'static dyn Metric]) -> Self {
+        ScoreFormula {
+            expression: metrics
+                .iter()
+                .map(|metric| metric.name())
+                .collect::<Vec<_>>()
+                .join(" * "),
+            terms: vec![
+                metrics
+                    .iter()
+                    .map(|metric| Factor::Metric(*metric))
+                    .collect(),
+            ],
+        }
+    }
+
+    /// The metrics the score is computed from, each once, in formula order.
+    pub fn metrics(&self) -> Vec<&'static dyn Metric> {
+        let mut metrics: Vec<&'static dyn Metric> = Vec::new();
+        for factor in self.terms.iter().flatten() {
+            if let Factor::Metric(metric) | Factor::Normalized(metric) = factor
+                && !metrics.iter().any(|m| m.name() == metric.name())
+            {
+                metrics.push(*metric);
+            }
+        }
+        metrics
+    }
+
+    /// Whether the score depends on other files' values through `norm()`.
+    pub fn normalizes(&self) -> bool {
+        self.terms
+            .iter()
+            .flatten()
+            .any(|factor| matches!(factor, Factor::Normalized(_)))
+    }
+
+    /// The score for one file's metric values. `maxima` holds the highest value
+    /// of each metric among all files; `norm()` of a metric without one is 0.
+    pub fn evaluate(
+        &self,
+        values: &BTreeMap<String, usize>,
+        maxima: &BTreeMap<String, usize>,
+    ) -> usize {
+        let value = |metric: &dyn Metric| values.get(metric.name()).copied().unwrap_or(0) as f64;
+        let score: f64 = self
+            .terms
+            .iter()
+            .map(|term| {
+                term.iter()
+                    .map(|factor| match factor {
+                        Factor::Number(number) => *number,
+                        Factor::Metric(metric) => value(*metric),
+                        Factor::Normalized(metric) => {
+                            match maxima.get(metric.name()).copied().unwrap_or(0) {
+                                0 => 0.0,
+                                max => value(*metric) * 100.0 / max as f64,
+                            }
+                        }
+                    })
+                    .product::<f64>()
+            })
+            .sum();
+        score.max(0.0).round() as usize
+    }
+
+    /// The contributing metric values, e.g. `complexity 12, churn 5`.
+    pub fn explain(&self, values: &BTreeMap<String, usize>) -> String {
+        self.metrics()
+            .iter()
+            .map(|metric| {
+                let value = values.get(metric.name()).copied().unwrap_or(0);
+                format!("{} {value}", metric.name())
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
@@ -0,0 +1,244 @@
+use crate::analyzer::{Analyzer, aggregate_values};
+use crate::git::commit_at;
+use crate::metrics::Metric;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// What each series of a trend follows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TrendScope {
+    /// The aggregated value over all files.
+    Total,
+    /// Each directory, as grouped by `Analyzer::dirs`.
+    Dir,
+    File,
+}
+
+/// A commit the metrics were evaluated at.
+#[derive(Clone, Debug, Serialize)]
+pub struct Sample {
+    pub commit: String,
+    /// Commit time, seconds since the epoch.
+    pub timestamp: u64,
+    pub date: String,
+}
+
+/// One metric of one file, directory or the total over the samples, oldest first.
+/// A value is `None` where the file or directory didn't exist.
+#[derive(Clone, Debug, Serialize)]
+pub struct Series {
+    /// `None` for the total.
+    pub path: Option<PathBuf>,
+    pub metric: &'static str,
+    pub unit: &'static str,
+    pub values: Vec<Option<usize>>,
+}
+
+/// A series with its samples' dates and commits, as written to JSON.
+#[derive(Serialize)]
+pub struct SeriesRecord<'a> {
+    pub path: Option<&'a PathBuf>,
+    pub metric: &'static str,
+    pub unit: &'static str,
+    pub points: Vec<Point<'a>>,
+}
+
+#[derive(Serialize)]
+pub struct Point<'a> {
+    pub date: &'a str,
+    pub commit: &'a str,
+    pub value: Option<usize>,
+}
+
+impl Series {
+    /// The series paired with the `samples` it was evaluated at.
+    pub fn record<'a>(&'a self, samples: &'a [Sample]) -> SeriesRecord<'a> {
+        SeriesRecord {
+            path: self.path.as_ref(),
+            metric: self.metric,
+            unit: self.unit,
+            points: samples
+                .iter()
+                .zip(&self.values)
+                .map(|(sample, value)| Point {
+                    date: &sample.date,
+                    commit: &sample.commit,
+                    value: *value,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn first(&self) -> Option<usize> {
+        self.values.iter().flatten().next().copied()
+    }
+
+    pub fn last(&self) -> Option<usize> {
+        self.values.last().copied().flatten()
+    }
+}
+
+/// Parses an interval such as `12h`, `3d`, `2w`, `6m` or `1y` into seconds.
+/// Months are 30 days and years 365.
+pub fn parse_interval(text: &str) -> Result<u64, String> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let (count, unit) = text.split_at(split);
+    let seconds = match unit {
+        "h" => 3600,
+        "d" => 86400,
+        "w" => 7 * 86400,
+        "m" => 30 * 86400,
+        "y" => 365 * 86400,
+        _ => {
+            return Err(format!(
+                "invalid interval '{text}': expected a number and h, d, w, m or y, e.g. 2w"
+            ));
+        }
+    };
+    match count.parse::<u64>() {
+        Ok(count) if count > 0 => Ok(count * seconds),
+        _ => Err(format!(
+            "invalid interval '{text}': expected a positive count"
+        )),
+    }
+}
+
+/// Commits of `revision` at every `every` seconds going back from `until` to
+/// `since` (or to the first commit), oldest first. Each sample is the newest
+/// first-parent commit at that time; consecutive samples may share a commit.
+pub fn sample_commits(
+    analyzer: &Analyzer,
+    revision: &str,
+    since: Option<u64>,
+    until: u64,
+    every: u64,
+) -> Result<Vec<Sample>, String> {
+    let mut samples = Vec::new();
+    let mut time = until;
+    while since.is_none_or(|since| time >= since) {
+        let Some((commit, timestamp)) = commit_at(analyzer.path(), revision, time)? else {
+            break;
+        };
+        samples.push(Sample {
+            commit,
+            timestamp,
+            date: format_date(time),
+        });
+        match time.checked_sub(every) {
+            Some(older) => time = older,
+            None => break,
+        }
+    }
+    samples.reverse();
+    Ok(samples)
+}
+
+/// Evaluates the analyzer's metrics at every sample and arranges the results as
+/// series in `scope`, one per metric for each file or directory, ranked by the
+/// latest value of the first metric. Samples sharing a commit are evaluated once.
+pub fn trend(
+    analyzer: &Analyzer,
+    samples: &[Sample],
+    scope: TrendScope,
+) -> Result<Vec<Series>, String> {
+    let metrics = analyzer.selected_metrics();
+    let mut evaluated: BTreeMap<&str, BTreeMap<Option<PathBuf>, Vec<usize>>> = BTreeMap::new();
+    for sample in samples {
+        if evaluated.contains_key(sample.commit.as_str()) {
+            continue;
+        }
+        let rows = analyzer.aggregate(analyzer.analyze_commit(&sample.commit)?);
+        let values = match scope {
+            TrendScope::Total => BTreeMap::from([(
+                None,
+                aggregate_values(&metrics, rows.iter().map(|row| &row.values)),
+            )]),
+            TrendScope::Dir | TrendScope::File => rows
+                .into_iter()
+                .map(|row| (Some(row.path), row.values))
+                .collect(),
+        };
+        evaluated.insert(&sample.commit, values);
+    }
+
+    let mut paths: Vec<&Option<PathBuf>> = evaluated
+        .values()
+        .flat_map(|values| values.keys())
+        .collect();
+    paths.sort();
+    paths.dedup();
+    // Largest by the first metric's latest value first, like a report ranks
+    if let Some(latest) = samples
+        .last()
+        .map(|sample| &evaluated[sample.commit.as_str()])
+    {
+        paths
+            .sort_by_key(|path| std::cmp::Reverse(latest.get(*path).map_or(0, |values| values[0])));
+    }
+
+    let mut series = Vec::new();
+    for path in paths {
+        for (i, metric) in metrics.iter().enumerate() {
+            series.push(Series {
+                path: path.clone(),
+                metric: metric.name(),
+                unit: metric.unit(),
+                values: samples
+                    .iter()
+                    .map(|sample| {
+                        evaluated[sample.commit.as_str()]
+                            .get(path)
+                            .map(|values| values[i])
+                    })
+                    .collect(),
+            });
+        }
+    }
+    Ok(series)
+}
+
+/// The metrics a trend can follow: those computed from the files in a commit
+/// alone, without git history.
+pub fn supports(metric: &dyn Metric) -> bool {
+    !metric.needs_history()
+}
+
+/// A one-line chart of `values` scaled between their minimum and maximum, with
+/// blanks where there's no value.
+pub fn sparkline(values: &[Option<usize>]) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let min = values.iter().flatten().min().copied().unwrap_or(0);
+    let max = values.iter().flatten().max().copied().unwrap_or(0);
+    values
+        .iter()
+        .map(|value| match value {
+            None => ' ',
+            Some(_) if max == min => BARS[BARS.len() / 2],
+            Some(value) => BARS[(value - min) * (BARS.len() - 1) / (max - min)],
+        })
+        .collect()
+}
+
+/// `YYYY-MM-DD` (UTC) for seconds since the epoch.
+pub fn format_date(timestamp: u64) -> String {
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let days = (timestamp / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
@@ -0,0 +1,79 @@
+//! Baseline comparisons follow each metric's direction: a bus factor that goes
+//! down is the regression, unlike a line count.
+
+use mad_useful::Row;
+use mad_useful::baseline::{Baseline, ChangeKind};
+use mad_useful::metrics::{BusFactor, Lines, Metric};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+fn baseline(files: &[(&str, &str, usize)]) -> Baseline {
+    let mut baseline = Baseline {
+        version: 1,
+        files: BTreeMap::new(),
+    };
+    for (path, metric, value) in files {
+        baseline
+            .files
+            .entry(path.to_string())
+            .or_default()
+            .insert(metric.to_string(), *value);
+    }
+    baseline
+}
+
+fn row(path: &str, value: usize) -> Row {
+    Row {
+        path: PathBuf::from("root").join(path),
+        values: vec![value],
+        author: String::new(),
+        extra_info: vec![String::new()],
+        extras: vec![Default::default()],
+        files: 1,
+        former_paths: Vec::new(),
+    }
+}
+
+fn kinds(
+    metric: &'static dyn Metric,
+    baseline: &Baseline,
+    rows: &[Row],
+) -> Vec<(String, ChangeKind)> {
+    let mut kinds: Vec<_> = baseline
+        .compare(&[metric], rows, Path::new("root"))
+        .into_iter()
+        .map(|change| (change.path.display().to_string(), change.kind))
+        .collect();
+    kinds.sort_by(|a, b| a.0.cmp(&b.0));
+    kinds
+}
+
+#[test]
+fn bus_factor_regresses_downward() {
+    let before = baseline(&[
+        ("fewer", "bus-factor", 3),
+        ("more", "bus-factor", 1),
+        ("same", "bus-factor", 2),
+    ]);
+    let rows = [row("fewer", 1), row("more", 2), row("same", 2)];
+    assert_eq!(
+        kinds(&BusFactor, &before, &rows),
+        [
+            ("root/fewer".to_string(), ChangeKind::Regressed),
+            ("root/more".to_string(), ChangeKind::Improved),
+        ]
+    );
+}
+
+#[test]
+fn lines_regress_upward() {
+    let before = baseline(&[("grew", "lines", 10), ("shrank", "lines", 10)]);
+    let rows = [row("grew", 12), row("shrank", 8)];
+    assert_eq!(
+        kinds(&Lines, &before, &rows),
+        [
+            ("root/grew".to_string(), ChangeKind::Regressed),
+            ("root/shrank".to_string(), ChangeKind::Improved),
+        ]
+    );
+}
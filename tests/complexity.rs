@@ -0,0 +1,98 @@
+//! Cyclomatic complexity of the files in `tests/corpus/complexity`, counted by
+//! hand. Each file's header comment says which rules it exercises.
+
+use mad_useful::analysis::calculate_complexity;
+use mad_useful::file_utils::SourceFile;
+use std::path::Path;
+
+/// A function's name, header line and complexity.
+type Function<'a> = (&'a str, usize, usize);
+
+/// File, whole-file complexity, then its functions in order.
+const EXPECTED: &[(&str, usize, &[Function<'static>])] = &[
+    (
+        "rust.rs",
+        10,
+        &[("fmt", 7, 5), ("parse", 18, 6), ("apply", 34, 1)],
+    ),
+    ("c.c", 10, &[("classify", 6, 7), ("main", 24, 4)]),
+    (
+        "cpp.cpp",
+        6,
+        &[("Counter::count", 5, 4), ("Counter::~Counter", 14, 3)],
+    ),
+    ("Shapes.java", 6, &[("toString", 8, 2), ("area", 12, 5)]),
+    ("Service.cs", 6, &[("Count", 8, 6)]),
+    ("worker.kt", 7, &[("classify", 7, 7)]),
+    ("loader.swift", 6, &[("load", 5, 4), ("size", 13, 3)]),
+    ("Shapes.scala", 7, &[("area", 3, 4), ("total", 9, 4)]),
+    (
+        "app.js",
+        8,
+        &[
+            ("greet", 2, 3),
+            ("pick", 8, 2),
+            ("get", 13, 3),
+            ("(anonymous)", 23, 3),
+        ],
+    ),
+    ("api.ts", 9, &[("fetchAll", 7, 6), ("describe", 20, 4)]),
+    (
+        "tool.py",
+        11,
+        &[
+            ("grade", 7, 6),
+            ("load", 17, 3),
+            ("read", 18, 2),
+            ("render", 30, 3),
+        ],
+    ),
+    (
+        "worker.go",
+        10,
+        &[("Run", 4, 6), ("kind", 19, 4), ("(anonymous)", 26, 2)],
+    ),
+    (
+        "page.php",
+        10,
+        &[("title", 3, 6), ("render", 16, 4), ("(anonymous)", 22, 2)],
+    ),
+];
+
+fn read(name: &str) -> SourceFile {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/corpus/complexity")
+        .join(name);
+    SourceFile::read(&path).unwrap_or_else(|e| panic!("cannot read {name}: {e}"))
+}
+
+#[test]
+fn file_complexity() {
+    for (name, expected, _) in EXPECTED {
+        assert_eq!(calculate_complexity(&read(name)), *expected, "{name}");
+    }
+}
+
+#[test]
+fn function_complexity() {
+    for (name, _, expected) in EXPECTED {
+        let source = read(name);
+        let found: Vec<Function> = source
+            .functions()
+            .iter()
+            .map(|function| (function.name.as_str(), function.line, function.complexity))
+            .collect();
+        assert_eq!(found, *expected, "{name}");
+    }
+}
+
+#[test]
+fn long_lines_count_once() {
+    let short = SourceFile::from_bytes("a.rs".into(), b"fn f() { if a { } }\n");
+    let long = SourceFile::from_bytes(
+        "a.rs".into(),
+        b"fn f() { if a_much_longer_condition_name.is_some() { return; } }\n",
+    );
+    assert_eq!(calculate_complexity(&short), 2);
+    assert_eq!(calculate_complexity(&long), 2);
+}
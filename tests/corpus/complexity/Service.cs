@@ -0,0 +1,24 @@
+// C#: nullable types aren't ternaries unless a `:` follows; `??` and foreach branch.
+using System.Collections.Generic;
+
+public class Service
+{
+    private string? name;
+
+    public int? Count(List<int>? items)
+    {
+        int total = 0;
+        foreach (var item in items ?? new List<int>())
+        {
+            if (item > 0 && item < 100)
+            {
+                total += item;
+            }
+        }
+        lock (this)
+        {
+            name = "service";
+        }
+        return total > 0 ? total : null;
+    }
+}
@@ -0,0 +1,27 @@
+// Java: wildcards and lambdas aren't branches; lambda bodies belong to their method.
+import java.util.List;
+
+public class Shapes {
+    private final List<? extends Shape> shapes;
+
+    @Override
+    public String toString() {
+        return shapes.isEmpty() ? "none" : shapes.toString();
+    }
+
+    public int area(int minimum) throws IllegalStateException, IllegalArgumentException {
+        int total = 0;
+        for (Shape shape : shapes) {
+            if (shape == null) {
+                throw new IllegalStateException("missing shape");
+            }
+            total += shape.area();
+        }
+        shapes.forEach(shape -> {
+            while (shape.grow() && minimum > 0) {
+                shape.shrink();
+            }
+        });
+        return total;
+    }
+}
@@ -0,0 +1,22 @@
+// Scala: every `case` but the catch-all `_` arm branches; `=>` doesn't.
+object Shapes {
+  def area(shape: Shape): Double = shape match {
+    case Circle(r) if r > 0 => math.Pi * r * r
+    case Square(s) => s * s
+    case _ => 0.0
+  }
+
+  def total(shapes: List[Shape]): Double = {
+    var sum = 0.0
+    for (s <- shapes) {
+      try {
+        sum += area(s)
+      } catch {
+        case e: ArithmeticException => sum = -1
+      }
+    }
+    sum
+  }
+
+  def twice(x: Int): Int = x * 2
+}
@@ -0,0 +1,29 @@
+// TypeScript: optional parameters and properties aren't ternaries.
+interface Options {
+  retries?: number;
+  verbose?: boolean;
+}
+
+export async function fetchAll(urls: string[], options?: Options): Promise<string[]> {
+  const retries = options?.retries ?? 3;
+  const results: string[] = [];
+  for (const url of urls) {
+    let attempt = 0;
+    while (attempt < retries && !results.includes(url)) {
+      attempt++;
+    }
+    results.push(options?.verbose ? `${url} after ${attempt}` : url);
+  }
+  return results;
+}
+
+export function describe(value: unknown, label?: string): string {
+  switch (typeof value) {
+    case "string":
+      return label ? `${label}: ${value}` : value;
+    case "number":
+      return String(value);
+    default:
+      return "other";
+  }
+}
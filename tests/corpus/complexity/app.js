@@ -0,0 +1,29 @@
+// JavaScript: optional chaining isn't a branch; `??` is.
+function greet(user) {
+  const name = user?.profile?.name ?? "guest";
+  if (!name) return "";
+  return `hello ${name}`;
+}
+
+const pick = (items, wanted) => {
+  return items.filter((item) => item.id === wanted || item.alias === wanted);
+};
+
+class Cache {
+  get(key) {
+    for (const [k, v] of this.entries) {
+      if (k === key) {
+        return v;
+      }
+    }
+    return undefined;
+  }
+}
+
+items.forEach(function (item) {
+  try {
+    item.run();
+  } catch (err) {
+    console.log(err ? err.message : "unknown");
+  }
+});
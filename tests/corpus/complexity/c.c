@@ -0,0 +1,35 @@
+/* C: `else if` is one branch, `case` arms branch and `default` doesn't. */
+#include <stdio.h>
+
+int classify(int n);
+
+int classify(int n)
+{
+    if (n < 0) {
+        return -1;
+    } else if (n == 0 || n == 1) {
+        return 0;
+    }
+    switch (n % 3) {
+    case 0:
+        return 3;
+    case 1:
+        break;
+    default:
+        return n > 100 ? 2 : 1;
+    }
+    return 1;
+}
+
+int main(void)
+{
+    int total = 0;
+    for (int i = 0; i < 10; i++) {
+        total += classify(i); // if (i) would not count
+    }
+    do {
+        total--;
+    } while (total > 5 && total % 2);
+    printf("if (%d) ? x : y\n", total);
+    return 0;
+}
@@ -0,0 +1,21 @@
+// C++: methods defined out of class, with templates in the signature.
+#include <map>
+#include <string>
+
+std::map<std::string, int> Counter::count(const std::string& text) const {
+    std::map<std::string, int> counts;
+    for (auto c : text) {
+        if (c == ' ' || c == '\n') continue;
+        counts[std::string(1, c)]++;
+    }
+    return counts;
+}
+
+Counter::~Counter() {
+    try {
+        flush();
+    } catch (const std::exception& e) {
+        log(e.what());
+    } catch (...) {
+    }
+}
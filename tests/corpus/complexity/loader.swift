@@ -0,0 +1,19 @@
+// Swift: optional types, `?[` and `try?` aren't ternaries; guard and `??` branch.
+struct Loader {
+    var cache: [String: Int]? = nil
+
+    func load(_ key: String?) -> Int? {
+        guard let key = key else {
+            return nil
+        }
+        let value = try? fetch(key)
+        return value ?? cache?[key] ?? 0
+    }
+
+    func size(of items: [Int]) -> String {
+        for item in items where item < 0 {
+            return "invalid"
+        }
+        return items.isEmpty ? "empty" : "\(items.count)"
+    }
+}
@@ -0,0 +1,26 @@
+<?php
+// PHP: `elseif`, `foreach`, `??` and `?:` branch; nullable types and `?->` don't.
+function title(?array $page): string
+{
+    $title = $page['title'] ?? 'Untitled';
+    if ($title === '') {
+        return 'Empty';
+    } elseif (strlen($title) > 80 and $page !== null) {
+        return substr($title, 0, 80);
+    }
+    return $page?->subtitle ?: $title;
+}
+
+class Menu
+{
+    public function render(array $items): string
+    {
+        $html = '';
+        foreach ($items as $item) {
+            $html .= $item['active'] ? '<b>' : '<i>';
+        }
+        return array_reduce($items, function ($carry, $item) {
+            return $carry || $item['visible'];
+        }, false) ? $html : '';
+    }
+}
@@ -0,0 +1,39 @@
+// Rust: `match` arms branch, except the `_` catch-all; `?` and closures don't.
+use std::fmt;
+
+struct Shape(u8);
+
+impl fmt::Display for Shape {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self.0 {
+            3 => "triangle",
+            4 if f.alternate() => "square",
+            4 => "quad",
+            _ => "polygon",
+        };
+        write!(f, "{name}")
+    }
+}
+
+fn parse(text: &str) -> Result<u32, std::num::ParseIntError> {
+    let value: u32 = text.trim().parse()?;
+    // if this comment counted, the total would be off
+    let label = "if while for && ||";
+    if value > 10 && !label.is_empty() || value == 0 {
+        return Ok(0);
+    }
+    let retry = || value.checked_mul(2);
+    for _ in 0..3 {
+        while let Some(doubled) = retry() {
+            return Ok(doubled);
+        }
+    }
+    Ok(value)
+}
+
+fn apply<F>(f: F) -> u32
+where
+    F: for<'a> Fn(&'a str) -> u32,
+{
+    f("")
+}
@@ -0,0 +1,33 @@
+"""Python: `elif`, `except`, `and`/`or` and comprehension clauses branch.
+
+if this docstring counted, the totals would be off
+"""
+
+
+def grade(score):
+    if score >= 90:
+        return "A"
+    elif score >= 80 and score < 90:
+        return "B"
+    elif score < 0 or score > 100:
+        raise ValueError("out of range")
+    return "C"
+
+
+def load(paths):
+    def read(path):
+        try:
+            with open(path) as handle:
+                return handle.read()
+        except OSError:
+            return ""
+
+    # for path in paths: would not count
+    return [read(path) for path in paths if path]
+
+
+class Report:
+    async def render(self, rows):
+        while rows:
+            row = rows.pop()
+            print(row if row else "-")
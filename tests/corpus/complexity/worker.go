@@ -0,0 +1,32 @@
+// Go: `select` and `switch` arms branch, `default` doesn't.
+package worker
+
+func (w *Worker) Run(jobs <-chan Job, done <-chan struct{}) error {
+	for {
+		select {
+		case job := <-jobs:
+			if err := w.handle(job); err != nil && !w.lenient {
+				return err
+			}
+		case <-done:
+			return nil
+		default:
+			w.idle()
+		}
+	}
+}
+
+func kind(n int) string {
+	switch {
+	case n < 0:
+		return "negative"
+	case n == 0 || n == 1:
+		return "small"
+	}
+	go func() {
+		if n > 100 {
+			log(n)
+		}
+	}()
+	return "large"
+}
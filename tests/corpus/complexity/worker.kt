@@ -0,0 +1,20 @@
+// Kotlin: `when` and the Elvis operator branch; nullable types and `?.` don't.
+package demo
+
+fun twice(x: Int) = x * 2
+
+class Box {
+    fun classify(value: Int?): String {
+        val n = value ?: return "none"
+        when {
+            n < 0 -> return "negative"
+            n == 0 -> return "zero"
+        }
+        for (i in 0 until n) {
+            if (i > 10 && n % i == 0) {
+                return "divisible"
+            }
+        }
+        return value?.toString() ?: "?"
+    }
+}
@@ -0,0 +1,36 @@
+//! Quality gates only decide the exit code: a gate that passes leaves the
+//! report exactly as it would be without it.
+
+use std::process::Command;
+
+fn madu(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_madu"))
+        .args(["--no-color", "--no-cache"])
+        .args(args)
+        .arg(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/corpus/complexity"))
+        .output()
+        .expect("cannot run madu");
+    assert!(output.status.success(), "madu {args:?} failed");
+    String::from_utf8(output.stdout).expect("output isn't UTF-8")
+}
+
+#[test]
+fn passing_gate_on_another_metric_keeps_the_report() {
+    assert_eq!(
+        madu(&["--complexity", "--fail-if", "size>100000000"]),
+        madu(&["--complexity"]),
+    );
+}
+
+#[test]
+fn passing_total_gate_keeps_the_order() {
+    assert_eq!(madu(&["--fail-if", "total.lines>100000000"]), madu(&[]));
+}
+
+#[test]
+fn passing_gate_keeps_json_output() {
+    assert_eq!(
+        madu(&["--format", "json", "--fail-if", "complexity>1000"]),
+        madu(&["--format", "json"]),
+    );
+}
@@ -0,0 +1,56 @@
+//! Code, comment, blank and mixed line counts of `--loc`, which follow cloc:
+//! mixed lines also count as code, and Python docstrings are comments.
+
+use mad_useful::file_utils::SourceFile;
+
+/// Code, comment, blank and mixed lines of `text` saved as `name`.
+fn counts(name: &str, text: &str) -> (usize, usize, usize, usize) {
+    let counts = SourceFile::from_bytes(name.into(), text.as_bytes()).line_counts();
+    (counts.code, counts.comment, counts.blank, counts.mixed)
+}
+
+#[test]
+fn rust() {
+    let text = r#"//! Crate doc.
+
+/* block
+   still */
+fn main() {
+    let s = "// no";
+    run(); // call
+}
+"#;
+    assert_eq!(counts("main.rs", text), (4, 3, 1, 1));
+}
+
+#[test]
+fn python() {
+    let text = r#"#!/usr/bin/env python
+"""Module doc.
+
+More."""
+
+def f(x):
+    '''Doc.'''
+    s = """text
+# not a comment"""
+    return s  # done
+"#;
+    assert_eq!(counts("tool.py", text), (4, 4, 2, 1));
+}
+
+#[test]
+fn shell() {
+    let text = r##"#!/bin/sh
+# usage
+
+echo $# "#x"
+n=${#1} # length
+"##;
+    assert_eq!(counts("run.sh", text), (2, 2, 1, 1));
+}
+
+#[test]
+fn unknown_languages_are_all_code() {
+    assert_eq!(counts("notes.xyz", "# a\n\nb\n"), (2, 0, 1, 0));
+}
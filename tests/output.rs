@@ -0,0 +1,64 @@
+//! CSV and TSV quoting: a cell holding the delimiter, a double quote or a line
+//! break is quoted, with quotes doubled.
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+const NAMES: &[&str] = &["a,c.txt", "a\"q.txt", "a\nn.txt", "a\tt.txt"];
+
+/// A directory of one-line files named `NAMES`, removed when dropped.
+struct Tree(PathBuf);
+
+impl Tree {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("madu-{name}-{}", std::process::id()));
+        fs::create_dir_all(&dir).expect("cannot create test directory");
+        for name in NAMES {
+            fs::write(dir.join(name), "x\n").expect("cannot write test file");
+        }
+        Tree(dir)
+    }
+
+    fn madu(&self, format: &str) -> String {
+        let output = Command::new(env!("CARGO_BIN_EXE_madu"))
+            .args(["--no-cache", "--format", format, "."])
+            .current_dir(&self.0)
+            .output()
+            .expect("cannot run madu");
+        assert!(output.status.success(), "madu --format {format} failed");
+        String::from_utf8(output.stdout).expect("output isn't UTF-8")
+    }
+}
+
+impl Drop for Tree {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[test]
+fn csv_quotes_commas_quotes_and_newlines() {
+    let tree = Tree::new("csv");
+    assert_eq!(
+        tree.madu("csv"),
+        "path,lines\n\
+         ./a\tt.txt,1\n\
+         \"./a\nn.txt\",1\n\
+         \"./a\"\"q.txt\",1\n\
+         \"./a,c.txt\",1\n"
+    );
+}
+
+#[test]
+fn tsv_quotes_tabs_quotes_and_newlines() {
+    let tree = Tree::new("tsv");
+    assert_eq!(
+        tree.madu("tsv"),
+        "path\tlines\n\
+         \"./a\tt.txt\"\t1\n\
+         \"./a\nn.txt\"\t1\n\
+         \"./a\"\"q.txt\"\t1\n\
+         ./a,c.txt\t1\n"
+    );
+}
@@ -0,0 +1,24 @@
+//! Start and end dates of `madu trend`: spans count back from now, anything
+//! else goes to git's date parser.
+
+use mad_useful::git::resolve_date;
+use std::path::Path;
+
+const NOW: u64 = 1_760_000_000;
+const DAY: u64 = 86400;
+
+fn root() -> &'static Path {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+}
+
+#[test]
+fn spans_count_back_from_now() {
+    assert_eq!(resolve_date(root(), "1y", NOW), Ok(NOW - 365 * DAY));
+    assert_eq!(resolve_date(root(), "2w", NOW), Ok(NOW - 14 * DAY));
+    assert_eq!(resolve_date(root(), "36h", NOW), Ok(NOW - 36 * 3600));
+}
+
+#[test]
+fn other_dates_are_read_by_git() {
+    assert_eq!(resolve_date(root(), "@1700000000", NOW), Ok(1_700_000_000));
+}